@@ -1,6 +1,9 @@
+use advent2020::grid::Grid;
+use advent2020::image::{self, Pixel};
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::error;
-use std::fs;
+use std::{env, fmt, fs};
 
 #[derive(Debug, Copy, Clone)]
 enum Object {
@@ -20,6 +23,36 @@ impl TryFrom<char> for Object {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Tile {
+    Empty,
+    Tree,
+    EmptyHit,
+    TreeHit,
+}
+
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tile::Empty => write!(f, "."),
+            Tile::Tree => write!(f, "#"),
+            Tile::EmptyHit => write!(f, "O"),
+            Tile::TreeHit => write!(f, "X"),
+        }
+    }
+}
+
+impl Pixel for Tile {
+    fn rgb(&self) -> [u8; 3] {
+        match self {
+            Tile::Empty => [255, 255, 255],
+            Tile::Tree => [34, 139, 34],
+            Tile::EmptyHit => [65, 105, 225],
+            Tile::TreeHit => [220, 20, 60],
+        }
+    }
+}
+
 trait Field {
     fn solve(&self, right: usize, down: usize) -> i64;
 }
@@ -56,6 +89,31 @@ impl GameField {
         let normalized_x = x % self.width;
         Some(self.objects[y][normalized_x])
     }
+
+    fn path(&self, right: usize, down: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        StepIterator::new(right, down).take_while(move |(_, y)| *y < self.height)
+    }
+
+    /// Lays the slope out as wide as the path goes (in whole pattern repeats)
+    /// and marks every visited cell. A path has to go down to ever end.
+    pub fn render(&self, right: usize, down: usize) -> Result<Grid<Tile>, Box<dyn error::Error>> {
+        if down == 0 {
+            return Err("Can't render a path that doesn't go down".into());
+        }
+
+        let visited: HashSet<_> = self.path(right, down).collect();
+        let last_x = visited.iter().map(|(x, _)| *x).max().unwrap_or(0);
+        let width = (last_x / self.width + 1) * self.width;
+
+        Ok(Grid::from_fn(width, self.height, |x, y| {
+            match (self.get(x, y).unwrap(), visited.contains(&(x, y))) {
+                (Object::Empty, false) => Tile::Empty,
+                (Object::Tree, false) => Tile::Tree,
+                (Object::Empty, true) => Tile::EmptyHit,
+                (Object::Tree, true) => Tile::TreeHit,
+            }
+        }))
+    }
 }
 
 impl Field for GameField {
@@ -101,10 +159,35 @@ impl Iterator for StepIterator {
     }
 }
 
-fn main() {
+fn render(
+    field: &GameField,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn error::Error>> {
+    let right = args.next().and_then(|v| v.parse().ok()).unwrap_or(3);
+    let down = args.next().and_then(|v| v.parse().ok()).unwrap_or(1);
+    let grid = field.render(right, down)?;
+
+    print!("{}", grid);
+
+    if let Some(path) = args.next() {
+        match image::save_ppm(&grid, 4, &path) {
+            Ok(()) => println!("Image saved to {}", path),
+            Err(e) => println!("Can't save image: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn error::Error>> {
     let input = fs::read_to_string("data/day03.txt").unwrap();
     let field = GameField::parse(&input).unwrap();
 
+    let mut args = env::args().skip(1);
+    if let Some("render") = args.next().as_deref() {
+        return render(&field, args);
+    }
+
     let task_a = field.solve(3, 1);
 
     let task_b = field.solve(1, 1)
@@ -114,4 +197,35 @@ fn main() {
         * field.solve(1, 2);
 
     println!("Task A: {}\nTask B: {}", task_a, task_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let field = GameField::parse("..#\n#..\n.#.").unwrap();
+        let grid = field.render(2, 1).unwrap();
+
+        assert_eq!(grid.to_string(), "O.#..#\n#.O#..\n.#..X.\n");
+    }
+
+    #[test]
+    fn test_render_matches_solve() {
+        let field = GameField::parse("..#\n#..\n.#.").unwrap();
+        let grid = field.render(2, 1).unwrap();
+        let hits = grid.iter().filter(|t| **t == Tile::TreeHit).count();
+
+        assert_eq!(hits as i64, field.solve(2, 1));
+    }
+
+    #[test]
+    fn test_render_flat_path() {
+        let field = GameField::parse("..#\n#..\n.#.").unwrap();
+
+        assert!(field.render(3, 0).is_err());
+    }
 }
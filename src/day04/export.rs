@@ -0,0 +1,330 @@
+use crate::schema::{Kind, Schema, Value, META_PREFIX};
+use crate::Passprt;
+use serde_json::{json, Map, Value as Json};
+use std::error::Error;
+use std::io::Write;
+
+/// Field name and raw value pairs of a passport loaded from an export.
+/// Typed values and validity flags are derived data and get recomputed
+/// against the schema when the record is turned back into a `Passprt`.
+pub type RawRecord = Vec<(String, String)>;
+
+const COMPLETE: &str = "complete";
+const VALID: &str = "valid";
+const EMPTY: &str = "empty";
+
+fn typed(value: &Value) -> Json {
+    match value {
+        Value::Int(value) => json!(value),
+        Value::Measure(value, unit) => json!({ "amount": value, "unit": unit }),
+        Value::Text(value) => json!(value),
+    }
+}
+
+pub fn to_json(passport: &Passprt) -> Json {
+    let mut fields = Map::new();
+
+    for rule in passport.schema.fields() {
+        if let Some(field) = passport.fields.get(rule.name.as_str()) {
+            let entry = match &field.value {
+                Ok(value) => json!({ "raw": field.raw, "value": typed(value) }),
+                Err(reason) => json!({ "raw": field.raw, "error": reason.to_string() }),
+            };
+            fields.insert(rule.name.clone(), entry);
+        }
+    }
+
+    json!({
+        "fields": fields,
+        COMPLETE: passport.valid(),
+        VALID: passport.valid_part2(),
+    })
+}
+
+pub fn write_jsonl<W: Write>(passports: &[Passprt], out: &mut W) -> Result<(), Box<dyn Error>> {
+    for passport in passports {
+        writeln!(out, "{}", to_json(passport))?;
+    }
+
+    Ok(())
+}
+
+pub fn read_jsonl(input: &str) -> Result<Vec<RawRecord>, Box<dyn Error>> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let record: Json = serde_json::from_str(line)?;
+            let fields = record
+                .get("fields")
+                .and_then(|f| f.as_object())
+                .ok_or_else(|| format!("Record without fields: {}", line))?;
+
+            fields
+                .iter()
+                .map(|(key, field)| {
+                    let raw = field
+                        .get("raw")
+                        .and_then(|raw| raw.as_str())
+                        .ok_or_else(|| format!("Field without raw value: {}", key))?;
+
+                    Ok((key.clone(), raw.to_string()))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// column for a schema field's typed value, or part of it
+fn meta(name: &str) -> String {
+    format!("{}{}", META_PREFIX, name)
+}
+
+/// One `<field>` column with the raw value per schema field, then columns
+/// prefixed with `@` for derived data: the typed value of every field
+/// (`@<field>`, or `@<field>.amount` and `@<field>.unit` for unit fields)
+/// and the validity flags `@complete` and `@valid`. Schema field names can't
+/// start with `@`, so the two never collide. An empty raw cell is an absent
+/// field unless it is named in the last column, `@empty`, which lists the
+/// fields present with an empty value.
+pub fn write_csv<W: Write>(
+    passports: &[Passprt],
+    schema: &Schema,
+    out: W,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    let mut header: Vec<String> = schema.fields().map(|rule| rule.name.clone()).collect();
+    for rule in schema.fields() {
+        match rule.kind {
+            Kind::Unit(_) => {
+                header.push(meta(&format!("{}.amount", rule.name)));
+                header.push(meta(&format!("{}.unit", rule.name)));
+            }
+            _ => header.push(meta(&rule.name)),
+        }
+    }
+    header.push(meta(COMPLETE));
+    header.push(meta(VALID));
+    header.push(meta(EMPTY));
+    writer.write_record(&header)?;
+
+    for passport in passports {
+        let mut row: Vec<String> = schema
+            .fields()
+            .map(|rule| passport.raw(&rule.name).unwrap_or("").to_string())
+            .collect();
+
+        for rule in schema.fields() {
+            let value = passport.value(&rule.name);
+            match (&rule.kind, value) {
+                (Kind::Unit(_), Some(Value::Measure(amount, unit))) => {
+                    row.push(amount.to_string());
+                    row.push(unit.to_string());
+                }
+                (Kind::Unit(_), _) => row.extend(vec![String::new(); 2]),
+                (_, Some(Value::Int(value))) => row.push(value.to_string()),
+                (_, Some(Value::Text(value))) => row.push(value.to_string()),
+                (_, Some(Value::Measure(..))) | (_, None) => row.push(String::new()),
+            }
+        }
+
+        row.push(passport.valid().to_string());
+        row.push(passport.valid_part2().to_string());
+        row.push(
+            schema
+                .fields()
+                .filter(|rule| passport.raw(&rule.name) == Some(""))
+                .map(|rule| rule.name.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        writer.write_record(&row)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads the raw value columns; `@` columns hold derived data and are
+/// skipped, except `@empty`. Empty cells are absent fields unless `@empty`
+/// names them.
+pub fn read_csv(input: &str) -> Result<Vec<RawRecord>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_reader(input.as_bytes());
+    let headers = reader.headers()?.clone();
+    let empty_column = headers.iter().position(|name| name == meta(EMPTY));
+    let columns = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| !name.starts_with(META_PREFIX))
+        .map(|(index, name)| (index, name.to_string()))
+        .collect::<Vec<_>>();
+
+    reader
+        .records()
+        .map(|row| {
+            let row = row?;
+            let empty: Vec<&str> = empty_column
+                .and_then(|index| row.get(index))
+                .map_or(vec![], |names| names.split_whitespace().collect());
+            let record = columns
+                .iter()
+                .filter_map(|(index, name)| match row.get(*index) {
+                    Some(raw) if !raw.is_empty() || empty.contains(&name.as_str()) => {
+                        Some((name.clone(), raw.to_string()))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            Ok(record)
+        })
+        .collect()
+}
+
+/// Picks the format by file extension: `.csv`, anything else is JSON Lines.
+pub fn read(path: &str, input: &str) -> Result<Vec<RawRecord>, Box<dyn Error>> {
+    if path.ends_with(".csv") {
+        read_csv(input)
+    } else {
+        read_jsonl(input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::parse(include_str!("../../data/day04.schema")).unwrap()
+    }
+
+    fn passports(schema: &Schema) -> Vec<Passprt<'_>> {
+        vec![
+            Passprt::parse("byr:1928 hgt:190cm hcl:#123abc", schema).unwrap(),
+            Passprt::parse("byr:1828 hgt:190mm cid:\"a,b\"", schema).unwrap(),
+        ]
+    }
+
+    fn pairs(record: &RawRecord) -> Vec<(&str, &str)> {
+        let mut pairs = record
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    #[test]
+    fn test_to_json() {
+        let schema = schema();
+        let passport = Passprt::parse("byr:1828 hgt:190cm", &schema).unwrap();
+
+        assert_eq!(
+            to_json(&passport),
+            json!({
+                "fields": {
+                    "byr": { "raw": "1828", "error": "out of range 1828 < 1920" },
+                    "hgt": { "raw": "190cm", "value": { "amount": 190, "unit": "cm" } },
+                },
+                "complete": false,
+                "valid": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_jsonl_roundtrip() {
+        let schema = schema();
+        let mut out = vec![];
+        write_jsonl(&passports(&schema), &mut out).unwrap();
+        let records = read_jsonl(&String::from_utf8(out).unwrap()).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            pairs(&records[0]),
+            vec![("byr", "1928"), ("hcl", "#123abc"), ("hgt", "190cm")]
+        );
+        assert_eq!(
+            pairs(&records[1]),
+            vec![("byr", "1828"), ("cid", "\"a,b\""), ("hgt", "190mm")]
+        );
+    }
+
+    #[test]
+    fn test_csv_roundtrip() {
+        let schema = schema();
+        let mut out = vec![];
+        write_csv(&passports(&schema), &schema, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+
+        assert_eq!(
+            lines.next().unwrap(),
+            "byr,iyr,eyr,hgt,hcl,ecl,pid,cid,\
+             @byr,@iyr,@eyr,@hgt.amount,@hgt.unit,@hcl,@ecl,@pid,@cid,@complete,@valid,@empty"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "1928,,,190cm,#123abc,,,,1928,,,190,cm,#123abc,,,,false,false,"
+        );
+
+        let records = read_csv(&text).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            pairs(&records[1]),
+            vec![("byr", "1828"), ("cid", "\"a,b\""), ("hgt", "190mm")]
+        );
+    }
+
+    #[test]
+    fn test_csv_field_named_like_metadata() {
+        let schema = Schema::parse("valid required any\nhgt_value optional any").unwrap();
+        let passport = Passprt::parse("valid:no hgt_value:3", &schema).unwrap();
+        let mut out = vec![];
+        write_csv(&[passport], &schema, &mut out).unwrap();
+        let records = read_csv(&String::from_utf8(out).unwrap()).unwrap();
+
+        assert_eq!(
+            pairs(&records[0]),
+            vec![("hgt_value", "3"), ("valid", "no")]
+        );
+    }
+
+    #[test]
+    fn test_csv_empty_value() {
+        let schema =
+            Schema::parse("byr required int 1920 2002\nhcl required any\ncid optional any")
+                .unwrap();
+        let passport = Passprt::parse("byr:1928 hcl: cid:", &schema).unwrap();
+        let mut out = vec![];
+        write_csv(&[passport], &schema, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            text.lines().nth(1).unwrap(),
+            "1928,,,1928,,,true,true,hcl cid"
+        );
+
+        let records = read_csv(&text).unwrap();
+        assert_eq!(
+            pairs(&records[0]),
+            vec![("byr", "1928"), ("cid", ""), ("hcl", "")]
+        );
+        let loaded = records[0].iter().map(|(k, v)| (k.as_str(), v.as_str()));
+        assert!(Passprt::from_pairs(loaded, &schema).unwrap().valid());
+
+        let records = read_csv("byr,hcl\n1928,\n").unwrap();
+        assert_eq!(pairs(&records[0]), vec![("byr", "1928")]);
+    }
+
+    #[test]
+    fn test_load_revalidates() {
+        let schema = schema();
+        let records = read_jsonl(r#"{"fields":{"byr":{"raw":"1828"}},"valid":true}"#).unwrap();
+        let pairs = records[0].iter().map(|(k, v)| (k.as_str(), v.as_str()));
+        let passport = Passprt::from_pairs(pairs, &schema).unwrap();
+
+        assert!(!passport.valid_part2());
+    }
+}
@@ -1,85 +1,39 @@
+mod export;
+mod report;
+mod schema;
+
 use advent2020::measure;
-use lazy_static::lazy_static;
-use regex::Regex;
+use report::Summary;
+use schema::{Mode, Reason, Schema, Value};
+use std::collections::HashMap;
+use std::env;
 use std::error::Error;
 use std::fs;
 
-#[derive(PartialEq, Debug)]
-enum Hgt {
-    Cm(u32),
-    In(u32),
-}
-
-fn parse_byr(input: &str) -> Option<u32> {
-    match input.parse::<u32>() {
-        Ok(value) if value >= 1920 && value <= 2002 => Some(value),
-        _ => None,
-    }
-}
-
-fn parse_iyr(input: &str) -> Option<u32> {
-    match input.parse::<u32>() {
-        Ok(value) if value >= 2010 && value <= 2020 => Some(value),
-        _ => None,
-    }
-}
-
-fn parse_eyr(input: &str) -> Option<u32> {
-    match input.parse::<u32>() {
-        Ok(value) if value >= 2020 && value <= 2030 => Some(value),
-        _ => None,
-    }
-}
-
-fn parse_hgt(input: &str) -> Option<Hgt> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^(\d+)(in|cm)$").unwrap();
-    }
-
-    let cap = RE.captures(input)?;
-    let value = cap.get(1).map(|m| m.as_str())?;
-    let kind = cap.get(2).map(|m| m.as_str())?;
-
-    match value.parse::<u32>() {
-        Ok(value) if kind == "in" && value >= 59 && value <= 76 => Some(Hgt::In(value)),
-        Ok(value) if kind == "cm" && value >= 150 && value <= 193 => Some(Hgt::Cm(value)),
-        _ => None,
-    }
+#[derive(Debug)]
+struct Field<'a> {
+    raw: &'a str,
+    value: Result<Value<'a>, Reason>,
 }
 
-fn parse_re<'a>(input: &'a str, re: &Regex) -> Option<&'a str> {
-    if re.is_match(input) {
-        Some(input)
-    } else {
-        None
-    }
+#[derive(Debug, PartialEq)]
+pub struct Failure<'a> {
+    pub field: &'a str,
+    pub reason: Reason,
 }
 
-#[derive(Default)]
 struct Passprt<'a> {
-    byr_raw: Option<&'a str>,
-    byr: Option<u32>,
-    iyr_raw: Option<&'a str>,
-    iyr: Option<u32>,
-    eyr_raw: Option<&'a str>,
-    eyr: Option<u32>,
-    hgt_raw: Option<&'a str>,
-    hgt: Option<Hgt>,
-    hcl_raw: Option<&'a str>,
-    hcl: Option<&'a str>,
-    ecl_raw: Option<&'a str>,
-    ecl: Option<&'a str>,
-    pid_raw: Option<&'a str>,
-    pid: Option<&'a str>,
-    cid_raw: Option<&'a str>,
+    schema: &'a Schema,
+    fields: HashMap<&'a str, Field<'a>>,
 }
 
 impl<'a> Passprt<'a> {
-    pub fn parse(input: &'a str) -> Result<Passprt<'a>, Box<dyn Error>> {
-        let mut passport = Self::default();
-        let items = input
-            .split(|c| c == '\n' || c == ' ')
-            .filter(|i| !i.is_empty());
+    pub fn parse(input: &'a str, schema: &'a Schema) -> Result<Passprt<'a>, Box<dyn Error>> {
+        let mut passport = Self {
+            schema,
+            fields: HashMap::new(),
+        };
+        let items = input.split(['\n', ' ']).filter(|i| !i.is_empty());
         for item in items {
             let mut entry = item.split(':');
             let key = entry
@@ -95,126 +49,320 @@ impl<'a> Passprt<'a> {
         Ok(passport)
     }
 
+    pub fn from_pairs<I>(pairs: I, schema: &'a Schema) -> Result<Passprt<'a>, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut passport = Self {
+            schema,
+            fields: HashMap::new(),
+        };
+        for (key, value) in pairs {
+            passport.set(key, value)?;
+        }
+
+        Ok(passport)
+    }
+
+    pub fn raw(&self, key: &str) -> Option<&'a str> {
+        self.fields.get(key).map(|f| f.raw)
+    }
+
+    pub fn value(&self, key: &str) -> Option<&Value<'a>> {
+        self.fields.get(key).and_then(|f| f.value.as_ref().ok())
+    }
+
+    /// Every schema field that fails: required ones that are absent and
+    /// present ones whose value does not pass the rule.
+    pub fn validate(&self) -> Vec<Failure<'_>> {
+        self.schema
+            .fields()
+            .filter_map(|rule| {
+                match self.fields.get(rule.name.as_str()) {
+                    None if rule.required => Some(Reason::Missing),
+                    None => None,
+                    Some(field) => field.value.as_ref().err().cloned(),
+                }
+                .map(|reason| Failure {
+                    field: &rule.name,
+                    reason,
+                })
+            })
+            .collect()
+    }
+
     pub fn valid(&self) -> bool {
-        self.byr_raw.is_some()
-            && self.iyr_raw.is_some()
-            && self.eyr_raw.is_some()
-            && self.hgt_raw.is_some()
-            && self.hcl_raw.is_some()
-            && self.ecl_raw.is_some()
-            && self.pid_raw.is_some()
+        self.schema
+            .fields()
+            .filter(|rule| rule.required)
+            .all(|rule| self.raw(&rule.name).is_some())
     }
 
+    /// Every required field is present and passes its rule. Optional fields
+    /// are reported by `validate` but never fail the passport.
     pub fn valid_part2(&self) -> bool {
-        self.byr.is_some()
-            && self.iyr.is_some()
-            && self.eyr.is_some()
-            && self.hgt.is_some()
-            && self.hcl.is_some()
-            && self.ecl.is_some()
-            && self.pid.is_some()
-    }
-
-    fn set(&mut self, key: &str, value: &'a str) -> Result<(), Box<dyn Error>> {
-        lazy_static! {
-            static ref RE_HCL: Regex = Regex::new(r"#[0-9a-f]{6}").unwrap();
-            static ref RE_ECL: Regex = Regex::new(r"^(amb|blu|brn|gry|grn|hzl|oth)$").unwrap();
-            static ref RE_PID: Regex = Regex::new(r"^[0-9]{9}$").unwrap();
-        }
+        self.schema
+            .fields()
+            .filter(|rule| rule.required)
+            .all(|rule| matches!(self.fields.get(rule.name.as_str()), Some(f) if f.value.is_ok()))
+    }
 
-        match key {
-            "byr" => {
-                self.byr_raw = Some(value);
-                self.byr = parse_byr(value);
-            }
-            "iyr" => {
-                self.iyr_raw = Some(value);
-                self.iyr = parse_iyr(value);
-            }
-            "eyr" => {
-                self.eyr_raw = Some(value);
-                self.eyr = parse_eyr(value);
-            }
-            "hgt" => {
-                self.hgt_raw = Some(value);
-                self.hgt = parse_hgt(value);
-            }
-            "hcl" => {
-                self.hcl_raw = Some(value);
-                self.hcl = parse_re(value, &RE_HCL);
-            }
-            "ecl" => {
-                self.ecl_raw = Some(value);
-                self.ecl = parse_re(value, &RE_ECL);
-            }
-            "pid" => {
-                self.pid_raw = Some(value);
-                self.pid = parse_re(value, &RE_PID);
-            }
-            "cid" => self.cid_raw = Some(value),
-            _ => return Err(format!("Invalid field: {}:{}", key, value).into()),
+    fn set(&mut self, key: &'a str, value: &'a str) -> Result<(), Box<dyn Error>> {
+        let rule = self
+            .schema
+            .get(key)
+            .ok_or_else(|| format!("Invalid field: {}:{}", key, value))?;
+
+        let field = Field {
+            raw: value,
+            value: rule.kind.check(value, self.schema.mode()),
         };
+        self.fields.insert(key, field);
 
         Ok(())
     }
 }
 
-fn main() {
-    let ((task_a, task_b), elapsed) = measure(|| {
-        let data = fs::read_to_string("data/day04.txt").unwrap();
-        let chunks = data.split("\n\n");
-        let passport = chunks
-            .map(|chunk| Passprt::parse(chunk))
-            .collect::<Result<Vec<_>, _>>()
-            .expect("bad input");
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut schema_path = "data/day04.schema".to_string();
+    let mut summary = false;
+    let mut mode = Mode::Lenient;
+    let mut load = None;
+    let mut jsonl_path = None;
+    let mut csv_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--summary" => summary = true,
+            "--strict" => mode = Mode::Strict,
+            "--load" => load = Some(args.next().ok_or("--load expects a path")?),
+            "--jsonl" => jsonl_path = Some(args.next().ok_or("--jsonl expects a path")?),
+            "--csv" => csv_path = Some(args.next().ok_or("--csv expects a path")?),
+            _ => schema_path = arg,
+        }
+    }
+
+    let schema = Schema::load(&schema_path)?.with_mode(mode);
+    let data = fs::read_to_string(load.as_deref().unwrap_or("data/day04.txt"))?;
+    let records = match load.as_deref() {
+        Some(path) => export::read(path, &data)?,
+        None => vec![],
+    };
+
+    let ((task_a, task_b, passport), elapsed) = measure(|| {
+        let passport = if load.is_some() {
+            records
+                .iter()
+                .map(|r| {
+                    Passprt::from_pairs(r.iter().map(|(k, v)| (k.as_str(), v.as_str())), &schema)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        } else {
+            data.split("\n\n")
+                .map(|chunk| Passprt::parse(chunk, &schema))
+                .collect::<Result<Vec<_>, _>>()
+        }
+        .expect("bad input");
         let task_a = passport.iter().filter(|p| p.valid()).count();
         let task_b = passport.iter().filter(|p| p.valid_part2()).count();
-        (task_a, task_b)
+        (task_a, task_b, passport)
     });
 
     println!(
         "task A: {}\ntask B: {}\nTotal time: {}μs ",
         task_a, task_b, elapsed
     );
+
+    if summary {
+        let summary = Summary::collect(passport.iter().map(|p| p.validate()));
+        print!("{}", summary);
+    }
+
+    if let Some(path) = jsonl_path {
+        export::write_jsonl(&passport, &mut fs::File::create(path)?)?;
+    }
+
+    if let Some(path) = csv_path {
+        export::write_csv(&passport, &schema, fs::File::create(path)?)?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn schema() -> Schema {
+        Schema::parse(include_str!("../../data/day04.schema")).unwrap()
+    }
+
     #[test]
     fn test_parse() {
+        let schema = schema();
         let input = "byr:1928";
-        let passport = Passprt::parse(&input).unwrap();
-        assert_eq!(passport.byr_raw, Some("1928"));
-        assert_eq!(passport.byr, Some(1928));
+        let passport = Passprt::parse(input, &schema).unwrap();
+        assert_eq!(passport.raw("byr"), Some("1928"));
+        assert_eq!(passport.value("byr"), Some(&Value::Int(1928)));
     }
 
     #[test]
     fn test_parse_fail() {
+        let schema = schema();
         let input = "byr:1928 foo:bar";
-        let passport = Passprt::parse(&input);
+        let passport = Passprt::parse(input, &schema);
         assert_eq!(passport.is_err(), true);
     }
 
     #[test]
     fn test_parse_invalid_year() {
+        let schema = schema();
         let input = "byr:1828";
-        let passport = Passprt::parse(&input).unwrap();
-        assert_eq!(passport.byr, None);
+        let passport = Passprt::parse(input, &schema).unwrap();
+        assert_eq!(passport.value("byr"), None);
     }
 
     #[test]
     fn test_parse_hgt() {
+        let schema = schema();
         let input = "hgt:190cm";
-        let passport = Passprt::parse(&input).unwrap();
-        assert_eq!(passport.hgt, Some(Hgt::Cm(190)));
+        let passport = Passprt::parse(input, &schema).unwrap();
+        assert_eq!(passport.value("hgt"), Some(&Value::Measure(190, "cm")));
     }
 
     #[test]
     fn test_parse_hgt_invalid() {
+        let schema = schema();
         let input = "hgt:195cm";
-        let passport = Passprt::parse(&input).unwrap();
-        assert_eq!(passport.hgt, None);
+        let passport = Passprt::parse(input, &schema).unwrap();
+        assert_eq!(passport.value("hgt"), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = schema();
+        let input = "byr:1828 hgt:190mm hcl:123abc ecl:brn pid:000000001 iyr:2015 eyr:2025";
+        let passport = Passprt::parse(input, &schema).unwrap();
+        let reasons = passport
+            .validate()
+            .iter()
+            .map(|f| format!("{}: {}", f.field, f.reason))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            reasons,
+            vec![
+                "byr: out of range 1828 < 1920",
+                "hgt: bad unit 'mm'",
+                "hcl: regex mismatch /#[0-9a-f]{6}/",
+            ]
+        );
+        assert!(passport.valid());
+        assert!(!passport.valid_part2());
+    }
+
+    #[test]
+    fn test_validate_missing() {
+        let schema = schema();
+        let passport = Passprt::parse("cid:1", &schema).unwrap();
+        let failures = passport.validate();
+
+        assert_eq!(failures.len(), 7);
+        assert!(failures.iter().all(|f| f.reason == Reason::Missing));
+    }
+
+    const DATA: &str = include_str!("../../data/day04.txt");
+
+    fn record(index: usize) -> &'static str {
+        DATA.split("\n\n").nth(index).unwrap()
+    }
+
+    #[test]
+    fn test_modes_agree_on_input() {
+        let lenient = schema();
+        let strict = schema().with_mode(Mode::Strict);
+        let count = |schema: &Schema| {
+            DATA.split("\n\n")
+                .map(|chunk| Passprt::parse(chunk, schema).unwrap())
+                .filter(|p| p.valid_part2())
+                .count()
+        };
+
+        assert_eq!(count(&lenient), 121);
+        assert_eq!(count(&strict), 121);
+    }
+
+    fn reasons(input: &str, schema: &Schema) -> Vec<String> {
+        Passprt::parse(input, schema)
+            .unwrap()
+            .validate()
+            .iter()
+            .map(|f| format!("{}: {}", f.field, f.reason))
+            .collect()
+    }
+
+    /// No record in the input has an `hcl` that the pattern finds but does
+    /// not fully match, which is why both modes count 121 above; record 1
+    /// is edited here to show the difference.
+    #[test]
+    fn test_strict_hcl() {
+        let input = record(1).replace("hcl:#fffffd", "hcl:x#123abcdef");
+
+        assert!(reasons(&input, &schema()).is_empty());
+        assert_eq!(
+            reasons(&input, &schema().with_mode(Mode::Strict)),
+            vec!["hcl: regex mismatch /^(?:#[0-9a-f]{6})$/"]
+        );
+    }
+
+    #[test]
+    fn test_anchored_pid() {
+        // pid:1655089174 has ten digits and is the only failing field
+        let input = record(247);
+        assert!(input.contains("pid:1655089174"));
+
+        assert_eq!(
+            reasons(input, &schema()),
+            vec!["pid: regex mismatch /^[0-9]{9}$/"]
+        );
+        assert_eq!(
+            reasons(input, &schema().with_mode(Mode::Strict)),
+            vec!["pid: regex mismatch /^(?:^[0-9]{9}$)$/"]
+        );
+
+        // with the anchors dropped from the schema only strict mode rejects it
+        let unanchored = include_str!("../../data/day04.schema").replace("^[0-9]{9}$", "[0-9]{9}");
+        let lenient = Schema::parse(&unanchored).unwrap();
+        assert!(reasons(input, &lenient).is_empty());
+        assert_eq!(
+            reasons(input, &lenient.with_mode(Mode::Strict)),
+            vec!["pid: regex mismatch /^(?:[0-9]{9})$/"]
+        );
+    }
+
+    #[test]
+    fn test_extra_field() {
+        let schema = Schema::parse("pid required any\ncnt required enum nl fr").unwrap();
+
+        let passport = Passprt::parse("pid:1 cnt:nl", &schema).unwrap();
+        assert!(passport.valid_part2());
+
+        let passport = Passprt::parse("pid:1 cnt:de", &schema).unwrap();
+        assert!(passport.valid());
+        assert!(!passport.valid_part2());
+
+        let passport = Passprt::parse("pid:1", &schema).unwrap();
+        assert!(!passport.valid());
+    }
+
+    #[test]
+    fn test_invalid_optional_field() {
+        let schema = Schema::parse("pid required any\ncnt optional enum nl fr").unwrap();
+        let passport = Passprt::parse("pid:1 cnt:de", &schema).unwrap();
+
+        assert_eq!(passport.validate().len(), 1);
+        assert_eq!(passport.validate()[0].field, "cnt");
+        assert!(passport.valid_part2());
     }
 }
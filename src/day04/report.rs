@@ -0,0 +1,86 @@
+use crate::Failure;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Failure reasons aggregated over a batch of passports.
+#[derive(Default)]
+pub struct Summary {
+    total: usize,
+    valid: usize,
+    reasons: BTreeMap<(String, &'static str), usize>,
+}
+
+impl Summary {
+    pub fn collect<'a, I>(batch: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<Failure<'a>>>,
+    {
+        let mut summary = Self::default();
+
+        for failures in batch {
+            summary.total += 1;
+            if failures.is_empty() {
+                summary.valid += 1;
+            }
+
+            for failure in failures {
+                let key = (failure.field.to_string(), failure.reason.category());
+                *summary.reasons.entry(key).or_insert(0) += 1;
+            }
+        }
+
+        summary
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Passports: {}, valid: {}, invalid: {}",
+            self.total,
+            self.valid,
+            self.total - self.valid
+        )?;
+
+        for ((field, category), count) in self.reasons.iter() {
+            writeln!(f, "{:>5} {}: {}", count, field, category)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::Reason;
+
+    #[test]
+    fn test_collect() {
+        let batch = vec![
+            vec![],
+            vec![Failure {
+                field: "byr",
+                reason: Reason::Missing,
+            }],
+            vec![
+                Failure {
+                    field: "byr",
+                    reason: Reason::Missing,
+                },
+                Failure {
+                    field: "hgt",
+                    reason: Reason::BadUnit("mm".to_string()),
+                },
+            ],
+        ];
+        let summary = Summary::collect(batch);
+
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.valid, 1);
+        assert_eq!(summary.reasons.len(), 2);
+        assert_eq!(summary.reasons[&("byr".to_string(), "missing")], 2);
+        assert_eq!(summary.reasons[&("hgt".to_string(), "bad unit")], 1);
+    }
+}
@@ -0,0 +1,387 @@
+use regex::Regex;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value<'a> {
+    Int(u32),
+    Measure(u32, &'a str),
+    Text(&'a str),
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Measure(value, unit) => write!(f, "{}{}", value, unit),
+            Value::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Reason {
+    Missing,
+    NotANumber,
+    TooSmall { value: u32, min: u32 },
+    TooLarge { value: u32, max: u32 },
+    BadUnit(String),
+    RegexMismatch(String),
+    NotAllowed(Vec<String>),
+}
+
+impl Reason {
+    /// Coarse reason name, used to aggregate failures across passports.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Reason::Missing => "missing",
+            Reason::NotANumber => "not a number",
+            Reason::TooSmall { .. } | Reason::TooLarge { .. } => "out of range",
+            Reason::BadUnit(_) => "bad unit",
+            Reason::RegexMismatch(_) => "regex mismatch",
+            Reason::NotAllowed(_) => "not allowed",
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Missing => write!(f, "missing"),
+            Reason::NotANumber => write!(f, "not a number"),
+            Reason::TooSmall { value, min } => write!(f, "out of range {} < {}", value, min),
+            Reason::TooLarge { value, max } => write!(f, "out of range {} > {}", value, max),
+            Reason::BadUnit(unit) => write!(f, "bad unit '{}'", unit),
+            Reason::RegexMismatch(re) => write!(f, "regex mismatch /{}/", re),
+            Reason::NotAllowed(values) => write!(f, "not one of {}", values.join(", ")),
+        }
+    }
+}
+
+fn check_range(input: &str, min: u32, max: u32) -> Result<u32, Reason> {
+    match input.parse::<u32>() {
+        Ok(value) if value < min => Err(Reason::TooSmall { value, min }),
+        Ok(value) if value > max => Err(Reason::TooLarge { value, max }),
+        Ok(value) => Ok(value),
+        Err(_) => Err(Reason::NotANumber),
+    }
+}
+
+#[derive(Debug)]
+pub struct UnitRange {
+    unit: String,
+    min: u32,
+    max: u32,
+}
+
+/// How `regex` field patterns are applied to values.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Mode {
+    /// Patterns are searched anywhere in the value, as written in the schema.
+    #[default]
+    Lenient,
+    /// Patterns must match the whole value.
+    Strict,
+}
+
+#[derive(Debug)]
+pub enum Kind {
+    Int { min: u32, max: u32 },
+    Unit(Vec<UnitRange>),
+    Regex { pattern: Regex, anchored: Regex },
+    Enum(Vec<String>),
+    Any,
+}
+
+impl Kind {
+    fn parse(kind: &str, args: &str) -> Result<Self, Box<dyn Error>> {
+        let words = args.split_whitespace().collect::<Vec<_>>();
+
+        match kind {
+            "int" => match words.as_slice() {
+                [min, max] => Ok(Kind::Int {
+                    min: min.parse()?,
+                    max: max.parse()?,
+                }),
+                _ => Err(format!("int expects <min> <max>, got: {}", args).into()),
+            },
+            "unit" if !words.is_empty() && words.len().is_multiple_of(3) => {
+                let units = words
+                    .chunks(3)
+                    .map(|chunk| -> Result<_, Box<dyn Error>> {
+                        Ok(UnitRange {
+                            unit: chunk[0].to_string(),
+                            min: chunk[1].parse()?,
+                            max: chunk[2].parse()?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Kind::Unit(units))
+            }
+            "unit" => Err(format!("unit expects <unit> <min> <max> ..., got: {}", args).into()),
+            "regex" if !args.is_empty() => Ok(Kind::Regex {
+                pattern: Regex::new(args)?,
+                anchored: Regex::new(&format!("^(?:{})$", args))?,
+            }),
+            "regex" => Err("regex expects a pattern".into()),
+            "enum" if !words.is_empty() => {
+                Ok(Kind::Enum(words.iter().map(|w| w.to_string()).collect()))
+            }
+            "enum" => Err("enum expects at least one value".into()),
+            "any" => Ok(Kind::Any),
+            _ => Err(format!("Unknown field type: {}", kind).into()),
+        }
+    }
+
+    pub fn check<'a>(&self, input: &'a str, mode: Mode) -> Result<Value<'a>, Reason> {
+        match self {
+            Kind::Int { min, max } => check_range(input, *min, *max).map(Value::Int),
+            Kind::Unit(units) => {
+                let split = input
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(input.len());
+                let (value, unit) = input.split_at(split);
+                if value.is_empty() {
+                    return Err(Reason::NotANumber);
+                }
+
+                let range = units
+                    .iter()
+                    .find(|u| u.unit == unit)
+                    .ok_or_else(|| Reason::BadUnit(unit.to_string()))?;
+
+                check_range(value, range.min, range.max).map(|value| Value::Measure(value, unit))
+            }
+            Kind::Regex { pattern, anchored } => {
+                let re = match mode {
+                    Mode::Lenient => pattern,
+                    Mode::Strict => anchored,
+                };
+
+                if re.is_match(input) {
+                    Ok(Value::Text(input))
+                } else {
+                    Err(Reason::RegexMismatch(re.as_str().to_string()))
+                }
+            }
+            Kind::Enum(values) if values.iter().any(|v| v == input) => Ok(Value::Text(input)),
+            Kind::Enum(values) => Err(Reason::NotAllowed(values.clone())),
+            Kind::Any => Ok(Value::Text(input)),
+        }
+    }
+}
+
+/// Start of export columns holding derived data, which field names can't use.
+pub const META_PREFIX: &str = "@";
+
+#[derive(Debug)]
+pub struct FieldRule {
+    pub name: String,
+    pub required: bool,
+    pub kind: Kind,
+}
+
+// first whitespace-separated token and the rest of the line after it
+fn token(input: &str) -> (&str, &str) {
+    let input = input.trim_start();
+    match input.split_once(char::is_whitespace) {
+        Some((token, rest)) => (token, rest.trim_start()),
+        None => (input, ""),
+    }
+}
+
+impl FieldRule {
+    /// `<name> <required|optional> <type> [args]`, tokens separated by any
+    /// whitespace; everything after the type is its argument spec.
+    fn parse(input: &str) -> Result<Self, Box<dyn Error>> {
+        let err = || format!("Invalid schema line: {}", input);
+
+        let (name, rest) = token(input);
+        let (required, rest) = token(rest);
+        let (kind, args) = token(rest);
+        if kind.is_empty() {
+            return Err(err().into());
+        }
+        if name.starts_with(META_PREFIX) || name.contains(':') {
+            return Err(format!("Invalid field name: {}", name).into());
+        }
+        let required = match required {
+            "required" => true,
+            "optional" => false,
+            _ => return Err(err().into()),
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            required,
+            kind: Kind::parse(kind, args.trim()).map_err(|e| format!("{}: {}", name, e))?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Schema {
+    fields: Vec<FieldRule>,
+    mode: Mode,
+}
+
+impl Schema {
+    pub fn parse(input: &str) -> Result<Self, Box<dyn Error>> {
+        let mut fields: Vec<FieldRule> = vec![];
+
+        for line in input.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let rule = FieldRule::parse(line)?;
+            if fields.iter().any(|f| f.name == rule.name) {
+                return Err(format!("Duplicate field: {}", rule.name).into());
+            }
+
+            fields.push(rule);
+        }
+
+        Ok(Self {
+            fields,
+            mode: Mode::default(),
+        })
+    }
+
+    pub fn with_mode(self, mode: Mode) -> Self {
+        Self { mode, ..self }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FieldRule> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = &FieldRule> {
+        self.fields.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_schema() {
+        let schema =
+            Schema::parse("# comment\n\nbyr required int 1920 2002\ncid optional any").unwrap();
+        let byr = schema.get("byr").unwrap();
+
+        assert!(byr.required);
+        assert_eq!(byr.kind.check("1920", Mode::Lenient), Ok(Value::Int(1920)));
+        assert_eq!(
+            byr.kind.check("1919", Mode::Lenient),
+            Err(Reason::TooSmall {
+                value: 1919,
+                min: 1920
+            })
+        );
+        assert!(!schema.get("cid").unwrap().required);
+        assert!(schema.get("foo").is_none());
+    }
+
+    #[test]
+    fn test_parse_schema_whitespace() {
+        let schema =
+            Schema::parse("byr  required\tint 1920   2002\nhcl\trequired  regex #[0-9a-f]{6} ")
+                .unwrap();
+
+        assert!(schema
+            .get("byr")
+            .unwrap()
+            .kind
+            .check("2002", Mode::Lenient)
+            .is_ok());
+        assert_eq!(
+            schema.get("hcl").unwrap().kind.check("#12", Mode::Lenient),
+            Err(Reason::RegexMismatch("#[0-9a-f]{6}".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_schema_fail() {
+        assert!(Schema::parse("byr required int 1920").is_err());
+        assert!(Schema::parse("byr maybe int 1920 2002").is_err());
+        assert!(Schema::parse("byr required float").is_err());
+        assert!(Schema::parse("byr required").is_err());
+        assert!(Schema::parse("byr required any\nbyr optional any").is_err());
+        assert!(Schema::parse("@valid required any").is_err());
+        assert!(Schema::parse("a:b required any").is_err());
+    }
+
+    #[test]
+    fn test_check_unit() {
+        let kind = Kind::parse("unit", "cm 150 193 in 59 76").unwrap();
+
+        assert_eq!(
+            kind.check("190cm", Mode::Lenient),
+            Ok(Value::Measure(190, "cm"))
+        );
+        assert_eq!(
+            kind.check("60in", Mode::Lenient),
+            Ok(Value::Measure(60, "in"))
+        );
+        assert_eq!(
+            kind.check("190in", Mode::Lenient),
+            Err(Reason::TooLarge {
+                value: 190,
+                max: 76
+            })
+        );
+        assert_eq!(
+            kind.check("190", Mode::Lenient),
+            Err(Reason::BadUnit("".to_string()))
+        );
+        assert_eq!(kind.check("cm", Mode::Lenient), Err(Reason::NotANumber));
+        assert_eq!(
+            kind.check("190mm", Mode::Lenient),
+            Err(Reason::BadUnit("mm".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_reason_display() {
+        let reason = Reason::TooSmall {
+            value: 1828,
+            min: 1920,
+        };
+        assert_eq!(reason.to_string(), "out of range 1828 < 1920");
+        assert_eq!(reason.category(), "out of range");
+    }
+
+    #[test]
+    fn test_check_regex_mode() {
+        let kind = Kind::parse("regex", "#[0-9a-f]{6}").unwrap();
+
+        assert!(kind.check("#123abc", Mode::Lenient).is_ok());
+        assert!(kind.check("#123abc", Mode::Strict).is_ok());
+        assert!(kind.check("x#123abcdef", Mode::Lenient).is_ok());
+        assert_eq!(
+            kind.check("x#123abcdef", Mode::Strict),
+            Err(Reason::RegexMismatch("^(?:#[0-9a-f]{6})$".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_check_enum() {
+        let kind = Kind::parse("enum", "amb blu").unwrap();
+
+        assert_eq!(kind.check("blu", Mode::Lenient), Ok(Value::Text("blu")));
+        assert!(kind.check("blue", Mode::Lenient).is_err());
+    }
+}
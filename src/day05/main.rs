@@ -1,6 +1,9 @@
+mod seat_map;
+
 use advent2020::measure;
+use seat_map::SeatMap;
 use std::error::Error;
-use std::fs;
+use std::{env, fs};
 
 fn binary(input: &str, one: char, zero: char) -> Option<u32> {
     let size = input.len();
@@ -18,6 +21,102 @@ fn binary(input: &str, one: char, zero: char) -> Option<u32> {
     Some(result)
 }
 
+fn unbinary(value: u32, size: u32, one: char, zero: char) -> String {
+    (0..size)
+        .rev()
+        .map(|shift| if value >> shift & 1 == 1 { one } else { zero })
+        .collect()
+}
+
+/// Seat layout of an aircraft: the pass encodes `row_bits` rows (`F`/`B`)
+/// followed by `column_bits` columns (`L`/`R`).
+#[derive(PartialEq, Debug, Clone, Copy)]
+struct Plane {
+    row_bits: u32,
+    column_bits: u32,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Self {
+            row_bits: 7,
+            column_bits: 3,
+        }
+    }
+}
+
+impl Plane {
+    /// Seat ids are `u32`, so planes need fewer than 32 bits in total.
+    pub fn new(row_bits: u32, column_bits: u32) -> Option<Self> {
+        if row_bits.checked_add(column_bits)? >= 32 {
+            return None;
+        }
+
+        Some(Self {
+            row_bits,
+            column_bits,
+        })
+    }
+
+    pub fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    pub fn columns(&self) -> u32 {
+        1 << self.column_bits
+    }
+
+    pub fn seats(&self) -> u32 {
+        1 << (self.row_bits + self.column_bits)
+    }
+
+    pub fn parse(&self, input: &str) -> Option<BoardPass> {
+        let split = self.row_bits as usize;
+        if input.len() != split + self.column_bits as usize || !input.is_char_boundary(split) {
+            return None;
+        }
+
+        let row = binary(&input[..split], 'B', 'F')?;
+        let column = binary(&input[split..], 'R', 'L')?;
+
+        Some(BoardPass::new(row, column))
+    }
+
+    pub fn encode(&self, pass: &BoardPass) -> Option<String> {
+        if !self.contains(pass) {
+            return None;
+        }
+
+        let row = unbinary(pass.row, self.row_bits, 'B', 'F');
+        let column = unbinary(pass.column, self.column_bits, 'R', 'L');
+
+        Some(row + &column)
+    }
+
+    pub fn seat_id(&self, pass: &BoardPass) -> Option<u32> {
+        if !self.contains(pass) {
+            return None;
+        }
+
+        Some(pass.row * self.columns() + pass.column)
+    }
+
+    pub fn pass(&self, seat_id: u32) -> Option<BoardPass> {
+        if seat_id >= self.seats() {
+            return None;
+        }
+
+        Some(BoardPass::new(
+            seat_id / self.columns(),
+            seat_id % self.columns(),
+        ))
+    }
+
+    fn contains(&self, pass: &BoardPass) -> bool {
+        pass.row < self.rows() && pass.column < self.columns()
+    }
+}
+
 #[derive(PartialEq, Debug)]
 struct BoardPass {
     row: u32,
@@ -28,17 +127,6 @@ impl BoardPass {
     fn new(row: u32, column: u32) -> Self {
         Self { row, column }
     }
-
-    pub fn parse(input: &str) -> Option<Self> {
-        let row = binary(&input[0..7], 'B', 'F')?;
-        let column = binary(&input[7..10], 'R', 'L')?;
-
-        Some(Self::new(row, column))
-    }
-
-    fn seatid(&self) -> u32 {
-        self.row * 8 + self.column
-    }
 }
 
 struct PairIter<I, K> {
@@ -92,16 +180,52 @@ where
     }
 }
 
+fn plane_from_args(args: &[String]) -> Result<Plane, Box<dyn Error>> {
+    match args {
+        [] => Ok(Plane::default()),
+        [rows, columns] => Plane::new(rows.parse()?, columns.parse()?)
+            .ok_or_else(|| "Row and column bits must add up to less than 32".into()),
+        _ => Err("Usage: day05 [--map] [<row bits> <column bits>]".into()),
+    }
+}
+
+fn print_map(map: &SeatMap) {
+    print!("{}", map);
+
+    let blocks = map
+        .empty_blocks()
+        .iter()
+        .map(|b| {
+            if b.start() == b.end() {
+                b.start().to_string()
+            } else {
+                format!("{}-{}", b.start(), b.end())
+            }
+        })
+        .collect::<Vec<_>>();
+
+    println!("Missing seats: {}", map.missing().len());
+    println!("Empty blocks: {}", blocks.join(", "));
+    println!("Enclosed: {:?}", map.enclosed());
+}
+
 fn main() {
-    let (result, elapsed) = measure(|| -> Result<(u32, u32), Box<dyn Error>> {
+    let (flags, args): (Vec<_>, Vec<_>) = env::args().skip(1).partition(|a| a.starts_with("--"));
+    let show_map = flags.iter().any(|f| f == "--map");
+
+    let (result, elapsed) = measure(|| -> Result<(u32, u32, String, SeatMap), Box<dyn Error>> {
+        let plane = plane_from_args(&args)?;
         let data = fs::read_to_string("data/day05.txt")?;
         let board_passes = data
             .lines()
-            .map(BoardPass::parse)
+            .map(|line| plane.parse(line))
             .collect::<Option<Vec<_>>>()
             .ok_or("Can\'t parse result")?;
 
-        let mut numbers: Vec<_> = board_passes.iter().map(|pass| pass.seatid()).collect();
+        let mut numbers: Vec<_> = board_passes
+            .iter()
+            .filter_map(|pass| plane.seat_id(pass))
+            .collect();
         numbers.sort_unstable();
 
         let task_a = numbers.iter().max().copied().ok_or("Task A: not found")?;
@@ -111,12 +235,27 @@ fn main() {
             .pair()
             .find_map(|(a, b)| if *b == *a + 1 { None } else { Some(a + 1) })
             .ok_or("Task B: not found")?;
+        let code = plane
+            .pass(task_b)
+            .and_then(|pass| plane.encode(&pass))
+            .ok_or("Task B: can't encode")?;
+
+        let map = SeatMap::new(plane, numbers);
 
-        Ok((task_a, task_b))
+        Ok((task_a, task_b, code, map))
     });
 
     match result {
-        Ok((a, b)) => println!("task A: {}\ntask B: {}\nTotal time: {}μs ", a, b, elapsed),
+        Ok((a, b, code, map)) => {
+            if show_map {
+                print_map(&map);
+            }
+
+            println!(
+                "task A: {}\ntask B: {} ({})\nTotal time: {}μs ",
+                a, b, code, elapsed
+            )
+        }
         Err(e) => println!("Error: {}", e),
     };
 }
@@ -124,6 +263,7 @@ fn main() {
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_binary() {
@@ -135,8 +275,73 @@ mod test {
     #[test]
     fn test_board_pass() {
         assert_eq!(
-            BoardPass::parse("BFFFBBFRRR").unwrap(),
+            Plane::default().parse("BFFFBBFRRR").unwrap(),
             BoardPass::new(70, 7)
         );
     }
+
+    #[test]
+    fn test_board_pass_invalid() {
+        let plane = Plane::default();
+        assert_eq!(plane.parse("BFFFBBFRR"), None);
+        assert_eq!(plane.parse("BFFFBBFRRRR"), None);
+        assert_eq!(plane.parse("BFFFBBRRRF"), None);
+    }
+
+    #[test]
+    fn test_encode() {
+        let plane = Plane::default();
+        let pass = BoardPass::new(44, 5);
+
+        assert_eq!(plane.encode(&pass).unwrap(), "FBFBBFFRLR");
+        assert_eq!(plane.seat_id(&pass), Some(357));
+        assert_eq!(plane.encode(&BoardPass::new(128, 0)), None);
+    }
+
+    #[test]
+    fn test_custom_plane() {
+        let plane = Plane::new(4, 2).unwrap();
+
+        assert_eq!(plane.parse("BFFBRL"), Some(BoardPass::new(9, 2)));
+        assert_eq!(plane.parse("FBFBBFFRLR"), None);
+        assert_eq!(plane.seat_id(&BoardPass::new(9, 2)), Some(38));
+        assert_eq!(plane.pass(64), None);
+    }
+
+    #[test]
+    fn test_plane_too_large() {
+        assert!(Plane::new(20, 11).is_some());
+        assert_eq!(Plane::new(20, 12), None);
+        assert_eq!(Plane::new(u32::MAX, 1), None);
+        assert!(plane_from_args(&["20".to_string(), "20".to_string()]).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_seat_id_roundtrip(row_bits in 0u32..12, column_bits in 0u32..6, seed: u32) {
+            let plane = Plane::new(row_bits, column_bits).unwrap();
+            let seat_id = seed % plane.seats();
+            let pass = plane.pass(seat_id).unwrap();
+
+            prop_assert_eq!(plane.seat_id(&pass), Some(seat_id));
+        }
+
+        #[test]
+        fn prop_codec_roundtrip(row_bits in 0u32..12, column_bits in 0u32..6, row: u32, column: u32) {
+            let plane = Plane::new(row_bits, column_bits).unwrap();
+            let pass = BoardPass::new(row % plane.rows(), column % plane.columns());
+            let encoded = plane.encode(&pass).unwrap();
+
+            prop_assert_eq!(encoded.len() as u32, row_bits + column_bits);
+            prop_assert_eq!(plane.parse(&encoded), Some(pass));
+        }
+
+        #[test]
+        fn prop_decode_roundtrip(input in "[FB]{7}[LR]{3}") {
+            let plane = Plane::default();
+            let pass = plane.parse(&input).unwrap();
+
+            prop_assert_eq!(plane.encode(&pass), Some(input));
+        }
+    }
 }
@@ -0,0 +1,116 @@
+use crate::Plane;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Occupancy of every seat of a plane, indexed by seat id.
+pub struct SeatMap {
+    plane: Plane,
+    occupied: Vec<bool>,
+}
+
+impl SeatMap {
+    pub fn new<I>(plane: Plane, seat_ids: I) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut occupied = vec![false; plane.seats() as usize];
+        for id in seat_ids {
+            if let Some(seat) = occupied.get_mut(id as usize) {
+                *seat = true;
+            }
+        }
+
+        Self { plane, occupied }
+    }
+
+    pub fn is_occupied(&self, seat_id: u32) -> bool {
+        self.occupied
+            .get(seat_id as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    pub fn missing(&self) -> Vec<u32> {
+        (0..self.plane.seats())
+            .filter(|id| !self.is_occupied(*id))
+            .collect()
+    }
+
+    /// Runs of consecutive missing seat ids.
+    pub fn empty_blocks(&self) -> Vec<RangeInclusive<u32>> {
+        let mut blocks: Vec<RangeInclusive<u32>> = vec![];
+
+        for id in self.missing() {
+            match blocks.last_mut() {
+                Some(block) if *block.end() + 1 == id => *block = *block.start()..=id,
+                _ => blocks.push(id..=id),
+            }
+        }
+
+        blocks
+    }
+
+    /// Missing seats whose ids on both sides are occupied.
+    pub fn enclosed(&self) -> Vec<u32> {
+        self.missing()
+            .into_iter()
+            .filter(|id| *id > 0 && self.is_occupied(id - 1) && self.is_occupied(id + 1))
+            .collect()
+    }
+}
+
+impl fmt::Display for SeatMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let columns = self.plane.columns();
+
+        for row in 0..self.plane.rows() {
+            let seats: String = (0..columns)
+                .map(|column| {
+                    if self.is_occupied(row * columns + column) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            let taken = seats.chars().filter(|c| *c == '#').count();
+
+            writeln!(f, "{:>4} {} {:>3}/{}", row, seats, taken, columns)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn map() -> SeatMap {
+        // 4 rows x 2 columns: ids 0..8
+        SeatMap::new(Plane::new(2, 1).unwrap(), vec![2, 3, 5, 7])
+    }
+
+    #[test]
+    fn test_missing() {
+        assert_eq!(map().missing(), vec![0, 1, 4, 6]);
+    }
+
+    #[test]
+    fn test_empty_blocks() {
+        assert_eq!(map().empty_blocks(), vec![0..=1, 4..=4, 6..=6]);
+    }
+
+    #[test]
+    fn test_enclosed() {
+        assert_eq!(map().enclosed(), vec![4, 6]);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            map().to_string(),
+            "   0 ..   0/2\n   1 ##   2/2\n   2 .#   1/2\n   3 .#   1/2\n"
+        );
+    }
+}
@@ -1,69 +1,169 @@
 use advent2020::measure;
-use std::collections::HashSet;
-use std::fs;
+use std::{env, fs};
 
-struct Group {
-    group_answers: HashSet<char>,
-    people: Vec<HashSet<char>>,
-}
+/// Set of answered questions `a`..=`z`, one bit per question.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+struct Answers(u32);
+
+impl Answers {
+    const ALL: Answers = Answers((1 << 26) - 1);
 
-impl Group {
     pub fn parse(input: &str) -> Self {
-        let mut group_answers = HashSet::new();
-        let mut people = vec![];
+        input
+            .chars()
+            .filter_map(Self::bit)
+            .fold(Self::default(), |acc, bit| Answers(acc.0 | bit))
+    }
 
-        for p in input.lines() {
-            let mut person = HashSet::new();
+    fn bit(c: char) -> Option<u32> {
+        match c {
+            'a'..='z' => Some(1 << (c as u32 - 'a' as u32)),
+            _ => None,
+        }
+    }
 
-            for c in p.chars() {
-                if let 'a'..='z' = c {
-                    group_answers.insert(c);
-                    person.insert(c);
-                }
-            }
+    pub fn union(self, other: Self) -> Self {
+        Answers(self.0 | other.0)
+    }
 
-            if !person.is_empty() {
-                people.push(person);
-            }
-        }
+    pub fn intersection(self, other: Self) -> Self {
+        Answers(self.0 & other.0)
+    }
 
-        Self {
-            group_answers,
-            people,
-        }
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, c: char) -> bool {
+        Self::bit(c).map(|bit| self.0 & bit != 0).unwrap_or(false)
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = char> {
+        ('a'..='z').filter(move |c| self.contains(*c))
+    }
+}
+
+struct Group {
+    people: Vec<Answers>,
+}
+
+impl Group {
+    pub fn parse(input: &str) -> Self {
+        let people = input
+            .lines()
+            .map(Answers::parse)
+            .filter(|person| !person.is_empty())
+            .collect();
+
+        Self { people }
     }
 
     pub fn any_answered(&self) -> usize {
-        self.group_answers.len()
+        self.union().len()
     }
 
     pub fn all_answered(&self) -> usize {
-        let total = self.people.len();
+        self.intersection().len()
+    }
 
-        self.group_answers
+    pub fn union(&self) -> Answers {
+        self.people
             .iter()
-            .filter(|answer| self.answered_to(answer) == total)
-            .count()
+            .fold(Answers::default(), |acc, p| acc.union(*p))
     }
 
-    fn answered_to(&self, answer: &char) -> usize {
+    pub fn intersection(&self) -> Answers {
+        if self.people.is_empty() {
+            return Answers::default();
+        }
+
         self.people
             .iter()
-            .filter(|person| person.contains(answer))
-            .count()
+            .fold(Answers::ALL, |acc, p| acc.intersection(*p))
+    }
+
+    /// Questions answered by at least `k` people of the group. Keeps one mask
+    /// per threshold `0..=k`, so it costs O(people × k) word operations.
+    pub fn answered_by_at_least(&self, k: usize) -> Answers {
+        let mut at_least = vec![Answers::default(); k + 1];
+        at_least[0] = Answers::ALL;
+
+        for person in self.people.iter() {
+            for j in (1..=k).rev() {
+                at_least[j] = at_least[j].union(at_least[j - 1].intersection(*person));
+            }
+        }
+
+        at_least[k]
+    }
+
+    pub fn majority(&self) -> Answers {
+        self.answered_by_at_least(self.people.len() / 2 + 1)
+    }
+}
+
+/// Per question counts over a batch of groups.
+#[derive(Default, Debug)]
+struct Histogram {
+    people: [usize; 26],
+    any: [usize; 26],
+    majority: [usize; 26],
+    all: [usize; 26],
+}
+
+impl Histogram {
+    pub fn collect(groups: &[Group]) -> Self {
+        let mut histogram = Self::default();
+        let count = |target: &mut [usize; 26], answers: Answers| {
+            for c in answers.iter() {
+                target[(c as u8 - b'a') as usize] += 1;
+            }
+        };
+
+        for group in groups {
+            for person in group.people.iter() {
+                count(&mut histogram.people, *person);
+            }
+
+            count(&mut histogram.any, group.union());
+            count(&mut histogram.majority, group.majority());
+            count(&mut histogram.all, group.intersection());
+        }
+
+        histogram
+    }
+
+    pub fn print(&self) {
+        println!("question   people   any  majority   all");
+        for (i, c) in ('a'..='z').enumerate() {
+            println!(
+                "{:>8} {:>8} {:>5} {:>9} {:>5}",
+                c, self.people[i], self.any[i], self.majority[i], self.all[i]
+            );
+        }
     }
 }
 
 fn main() {
-    let ((a, b), elapsed) = measure(|| {
+    let stats = env::args().skip(1).any(|a| a == "--stats");
+
+    let ((a, b, groups), elapsed) = measure(|| {
         let data = fs::read_to_string("data/day06.txt").expect("File not found");
         let groups = data.split("\n\n").map(Group::parse).collect::<Vec<_>>();
         let task_a: usize = groups.iter().map(|g| g.any_answered()).sum();
         let task_b: usize = groups.iter().map(|g| g.all_answered()).sum();
 
-        (task_a, task_b)
+        (task_a, task_b, groups)
     });
 
+    if stats {
+        Histogram::collect(&groups).print();
+    }
+
     println!("task A: {}\ntask B: {}\nTotal time: {}μs ", a, b, elapsed);
 }
 
@@ -77,4 +177,40 @@ mod test {
         assert_eq!(group.any_answered(), 3);
         assert_eq!(group.all_answered(), 3);
     }
+
+    #[test]
+    fn test_answers_mask() {
+        let a = Answers::parse("abz");
+        let b = Answers::parse("bcz");
+
+        assert_eq!(a.union(b).iter().collect::<String>(), "abcz");
+        assert_eq!(a.intersection(b).iter().collect::<String>(), "bz");
+        assert!(!a.contains('c'));
+        assert!(!a.contains('?'));
+    }
+
+    #[test]
+    fn test_answered_by_at_least() {
+        let group = Group::parse("abc\nab\na\nd");
+        let answered = |k| group.answered_by_at_least(k).iter().collect::<String>();
+
+        assert_eq!(answered(0), ('a'..='z').collect::<String>());
+        assert_eq!(answered(1), "abcd");
+        assert_eq!(answered(2), "ab");
+        assert_eq!(answered(3), "a");
+        assert_eq!(answered(4), "");
+        assert_eq!(group.majority().iter().collect::<String>(), "a");
+    }
+
+    #[test]
+    fn test_histogram() {
+        let groups = vec![Group::parse("ab\nb"), Group::parse("b\nc")];
+        let histogram = Histogram::collect(&groups);
+
+        assert_eq!(histogram.people[1], 3);
+        assert_eq!(histogram.any[1], 2);
+        assert_eq!(histogram.majority[1], 1);
+        assert_eq!(histogram.all[1], 1);
+        assert_eq!(histogram.any[2], 1);
+    }
 }
@@ -0,0 +1,142 @@
+use crate::graph::BagGraph;
+use std::collections::HashSet;
+
+/// Part of the graph to export.
+pub enum Scope<'s> {
+    All,
+    /// The colour and everything it contains.
+    From(&'s str),
+    /// The colour and everything that contains it.
+    To(&'s str),
+}
+
+pub enum Format {
+    Dot,
+    Mermaid,
+}
+
+// sorted bags of the scope and the edges between them, in rule order
+fn subgraph<'a>(
+    graph: &BagGraph<'a>,
+    scope: &Scope,
+) -> (Vec<&'a str>, Vec<(&'a str, &'a str, usize)>) {
+    let mut bags: Vec<&str> = match scope {
+        Scope::All => graph.bags().collect(),
+        Scope::From(bag) => graph
+            .inside_of(bag)
+            .into_iter()
+            .chain(graph.bag(bag))
+            .collect(),
+        Scope::To(bag) => graph
+            .containers_of(bag, None)
+            .into_iter()
+            .chain(graph.bag(bag))
+            .collect(),
+    };
+    bags.sort_unstable();
+    bags.dedup();
+
+    let included = bags.iter().copied().collect::<HashSet<_>>();
+    let edges = bags
+        .iter()
+        .flat_map(|outer| {
+            graph
+                .contents(outer)
+                .iter()
+                .map(move |(inner, count)| (*outer, *inner, *count))
+        })
+        .filter(|(_, inner, _)| included.contains(inner))
+        .collect();
+
+    (bags, edges)
+}
+
+fn mermaid_id(bag: &str) -> String {
+    bag.replace(' ', "_")
+}
+
+pub fn export(graph: &BagGraph, scope: &Scope, format: &Format) -> String {
+    let (bags, edges) = subgraph(graph, scope);
+    let mut out = vec![];
+
+    match format {
+        Format::Dot => {
+            out.push("digraph bags {".to_string());
+            out.extend(bags.iter().map(|bag| format!("    \"{}\";", bag)));
+            out.extend(edges.iter().map(|(outer, inner, count)| {
+                format!("    \"{}\" -> \"{}\" [label=\"{}\"];", outer, inner, count)
+            }));
+            out.push("}".to_string());
+        }
+        Format::Mermaid => {
+            out.push("graph TD".to_string());
+            out.extend(
+                bags.iter()
+                    .map(|bag| format!("    {}[\"{}\"]", mermaid_id(bag), bag)),
+            );
+            out.extend(edges.iter().map(|(outer, inner, count)| {
+                format!(
+                    "    {} -->|{}| {}",
+                    mermaid_id(outer),
+                    count,
+                    mermaid_id(inner)
+                )
+            }));
+        }
+    }
+
+    out.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RULES: &str = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 3 faded blue bags.
+faded blue bags contain no other bags.";
+
+    #[test]
+    fn test_dot_from() {
+        let graph = BagGraph::parse(RULES).unwrap();
+
+        assert_eq!(
+            export(&graph, &Scope::From("muted yellow"), &Format::Dot),
+            "digraph bags {
+    \"faded blue\";
+    \"muted yellow\";
+    \"shiny gold\";
+    \"muted yellow\" -> \"shiny gold\" [label=\"2\"];
+    \"muted yellow\" -> \"faded blue\" [label=\"9\"];
+    \"shiny gold\" -> \"faded blue\" [label=\"3\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_mermaid_to() {
+        let graph = BagGraph::parse(RULES).unwrap();
+
+        assert_eq!(
+            export(&graph, &Scope::To("bright white"), &Format::Mermaid),
+            "graph TD
+    bright_white[\"bright white\"]
+    light_red[\"light red\"]
+    light_red -->|1| bright_white
+"
+        );
+    }
+
+    #[test]
+    fn test_unknown_scope() {
+        let graph = BagGraph::parse(RULES).unwrap();
+
+        assert_eq!(
+            export(&graph, &Scope::From("dotted black"), &Format::Mermaid),
+            "graph TD\n"
+        );
+    }
+}
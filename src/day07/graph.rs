@@ -0,0 +1,493 @@
+use crate::parse_line;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum GraphError {
+    Syntax(String),
+    Duplicate(String),
+    /// Pairs of (outer, inner) where the inner colour has no rule.
+    Undefined(Vec<(String, String)>),
+    /// Colours of a containment cycle, outermost first and repeated at the end.
+    Cycle(Vec<String>),
+    /// The bag holds more bags than fit in a `usize`.
+    Overflow(String),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::Syntax(line) => write!(f, "Invalid rule: {}", line),
+            GraphError::Duplicate(bag) => write!(f, "Bag defined twice: {}", bag),
+            GraphError::Undefined(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(outer, inner)| format!("{} (in {})", inner, outer))
+                    .collect::<Vec<_>>();
+                write!(f, "Undefined bags: {}", pairs.join(", "))
+            }
+            GraphError::Cycle(bags) => write!(f, "Cycle: {}", bags.join(" -> ")),
+            GraphError::Overflow(bag) => write!(f, "Too many bags inside: {}", bag),
+        }
+    }
+}
+
+impl Error for GraphError {}
+
+pub struct BagGraph<'a> {
+    contents: HashMap<&'a str, Vec<(&'a str, usize)>>,
+    containers: HashMap<&'a str, Vec<&'a str>>,
+    // every bag comes before the bags it contains
+    order: Vec<&'a str>,
+}
+
+/// Everything inside a bag in pre-order, as (level, count, bag).
+pub struct Tree<'a> {
+    pub nodes: Vec<(usize, usize, &'a str)>,
+}
+
+impl<'a> BagGraph<'a> {
+    pub fn parse(input: &'a str) -> Result<Self, GraphError> {
+        let mut contents = HashMap::new();
+        let mut containers: HashMap<&str, Vec<&str>> = HashMap::new();
+
+        for line in input.lines() {
+            let (bag, inners) = parse_line(line).ok_or_else(|| GraphError::Syntax(line.into()))?;
+
+            for (inner, _) in inners.iter() {
+                containers.entry(inner).or_default().push(bag);
+            }
+
+            if contents.insert(bag, inners).is_some() {
+                return Err(GraphError::Duplicate(bag.into()));
+            }
+        }
+
+        let mut undefined = contents
+            .iter()
+            .flat_map(|(outer, inners)| inners.iter().map(move |(inner, _)| (outer, inner)))
+            .filter(|(_, inner)| !contents.contains_key(*inner))
+            .map(|(outer, inner)| (outer.to_string(), inner.to_string()))
+            .collect::<Vec<_>>();
+
+        if !undefined.is_empty() {
+            undefined.sort_unstable();
+            return Err(GraphError::Undefined(undefined));
+        }
+
+        let order = Self::sort(&contents, &containers)?;
+
+        Ok(Self {
+            contents,
+            containers,
+            order,
+        })
+    }
+
+    // Kahn's algorithm: repeatedly take a bag no remaining bag contains
+    fn sort(
+        contents: &HashMap<&'a str, Vec<(&'a str, usize)>>,
+        containers: &HashMap<&'a str, Vec<&'a str>>,
+    ) -> Result<Vec<&'a str>, GraphError> {
+        let mut incoming: HashMap<&str, usize> = contents
+            .keys()
+            .map(|bag| (*bag, containers.get(bag).map(|c| c.len()).unwrap_or(0)))
+            .collect();
+
+        let mut ready = incoming
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(bag, _)| *bag)
+            .collect::<Vec<_>>();
+        ready.sort_unstable();
+
+        let mut order = Vec::with_capacity(contents.len());
+        while let Some(bag) = ready.pop() {
+            order.push(bag);
+
+            for (inner, _) in contents[bag].iter() {
+                let n = incoming.get_mut(inner).unwrap();
+                *n -= 1;
+                if *n == 0 {
+                    ready.push(inner);
+                }
+            }
+        }
+
+        if order.len() == contents.len() {
+            return Ok(order);
+        }
+
+        // every bag left over has a container that is left over too, so walking
+        // up through containers has to come back to a bag already seen
+        let left = |bag: &str| incoming[bag] > 0;
+        let mut bag = incoming.keys().copied().filter(|b| left(b)).min().unwrap();
+        let mut path = vec![];
+
+        while !path.contains(&bag) {
+            path.push(bag);
+            bag = containers[bag]
+                .iter()
+                .copied()
+                .filter(|b| left(b))
+                .min()
+                .unwrap();
+        }
+
+        let from = path.iter().position(|b| *b == bag).unwrap();
+        let mut cycle = path[from..]
+            .iter()
+            .rev()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>();
+        cycle.insert(0, bag.to_string());
+
+        Err(GraphError::Cycle(cycle))
+    }
+
+    pub fn contents(&self, bag: &str) -> &[(&'a str, usize)] {
+        self.contents.get(bag).map(|c| c.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn containers(&self, bag: &str) -> &[&'a str] {
+        self.containers
+            .get(bag)
+            .map(|c| c.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn bags(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.order.iter().copied()
+    }
+
+    pub fn bag(&self, bag: &str) -> Option<&'a str> {
+        self.contents.get_key_value(bag).map(|(bag, _)| *bag)
+    }
+
+    /// Bags that end up inside `target`, at any depth.
+    pub fn inside_of(&self, target: &str) -> HashSet<&'a str> {
+        let mut found = HashSet::new();
+        let mut stack = vec![target];
+
+        while let Some(bag) = stack.pop() {
+            for (inner, _) in self.contents(bag) {
+                if found.insert(*inner) {
+                    stack.push(inner);
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Bags that eventually contain `target`, at most `depth` levels up.
+    pub fn containers_of(&self, target: &str, depth: Option<usize>) -> HashSet<&'a str> {
+        let mut found = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back((target, 0));
+
+        while let Some((bag, level)) = queue.pop_front() {
+            if depth.map(|d| level >= d).unwrap_or(false) {
+                continue;
+            }
+
+            for outer in self.containers(bag) {
+                if found.insert(*outer) {
+                    queue.push_back((outer, level + 1));
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Number of bags inside `target`, counting at most `depth` levels down.
+    pub fn total_inside(&self, target: &str, depth: Option<usize>) -> Result<usize, GraphError> {
+        // `None` for bags holding too many to count
+        let mut inside: HashMap<&str, Option<usize>> = HashMap::new();
+
+        match depth {
+            // a bag only depends on the bags it contains, so going from the
+            // innermost bags outwards everything needed is already known
+            None => {
+                for bag in self.order.iter().rev() {
+                    let value = self.sum_inside(bag, &inside);
+                    inside.insert(bag, value);
+                }
+            }
+            // no chain is longer than the number of bags
+            Some(depth) => {
+                for _ in 0..depth.min(self.order.len()) {
+                    inside = self
+                        .order
+                        .iter()
+                        .map(|bag| (*bag, self.sum_inside(bag, &inside)))
+                        .collect();
+                }
+            }
+        }
+
+        match inside.get(target) {
+            Some(Some(total)) => Ok(*total),
+            Some(None) => Err(GraphError::Overflow(target.into())),
+            None => Ok(0),
+        }
+    }
+
+    fn sum_inside(&self, bag: &str, inside: &HashMap<&str, Option<usize>>) -> Option<usize> {
+        self.contents(bag)
+            .iter()
+            .try_fold(0usize, |total, (inner, count)| {
+                let each = inside
+                    .get(inner)
+                    .copied()
+                    .unwrap_or(Some(0))?
+                    .checked_add(1)?;
+                total.checked_add(count.checked_mul(each)?)
+            })
+    }
+
+    /// Fewest nesting steps from `outer` down to `inner`, both ends included.
+    pub fn shortest_chain(&self, outer: &str, inner: &str) -> Option<Vec<&'a str>> {
+        let start = self.bag(outer)?;
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+
+        while let Some(bag) = queue.pop_front() {
+            if bag == inner {
+                let mut chain = vec![bag];
+                while let Some(prev) = previous.get(chain.last().unwrap()) {
+                    chain.push(prev);
+                }
+                chain.reverse();
+
+                return Some(chain);
+            }
+
+            for (next, _) in self.contents(bag) {
+                if *next != start && !previous.contains_key(next) {
+                    previous.insert(next, bag);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Most nesting steps from `outer` down to `inner`, both ends included.
+    pub fn longest_chain(&self, outer: &str, inner: &str) -> Option<Vec<&'a str>> {
+        let start = self.bag(outer)?;
+
+        // per bag: the length of the longest path down to `inner` and the next bag on it
+        let mut longest: HashMap<&str, (usize, Option<&str>)> = HashMap::new();
+        for bag in self.order.iter().rev() {
+            if *bag == inner {
+                longest.insert(bag, (0, None));
+                continue;
+            }
+
+            let best = self
+                .contents(bag)
+                .iter()
+                .filter_map(|(next, _)| longest.get(next).map(|(len, _)| (len + 1, *next)))
+                .fold(None, |best, candidate| match best {
+                    Some((len, _)) if len >= candidate.0 => best,
+                    _ => Some(candidate),
+                });
+
+            if let Some((len, next)) = best {
+                longest.insert(bag, (len, Some(next)));
+            }
+        }
+
+        let mut chain = vec![start];
+        let mut next = longest.get(start)?.1;
+        while let Some(bag) = next {
+            chain.push(bag);
+            next = longest[bag].1;
+        }
+
+        Some(chain)
+    }
+
+    pub fn tree(&self, bag: &'a str, depth: Option<usize>) -> Tree<'a> {
+        let mut nodes = vec![];
+        let mut stack = vec![(0, 1, bag)];
+
+        while let Some((level, count, bag)) = stack.pop() {
+            nodes.push((level, count, bag));
+
+            if depth.map(|d| level < d).unwrap_or(true) {
+                for (inner, n) in self.contents(bag).iter().rev() {
+                    stack.push((level + 1, *n, inner));
+                }
+            }
+        }
+
+        Tree { nodes }
+    }
+}
+
+impl fmt::Display for Tree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (level, count, bag) in self.nodes.iter() {
+            writeln!(f, "{}{} {}", "  ".repeat(*level), count, bag)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RULES: &str = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.";
+
+    #[test]
+    fn test_containers_of() {
+        let graph = BagGraph::parse(RULES).unwrap();
+
+        assert_eq!(graph.containers_of("shiny gold", None).len(), 4);
+        assert_eq!(graph.containers_of("shiny gold", Some(1)).len(), 2);
+        assert_eq!(graph.containers_of("light red", None).len(), 0);
+    }
+
+    #[test]
+    fn test_total_inside() {
+        let graph = BagGraph::parse(RULES).unwrap();
+
+        assert_eq!(graph.total_inside("shiny gold", None), Ok(32));
+        assert_eq!(graph.total_inside("shiny gold", Some(1)), Ok(3));
+        assert_eq!(graph.total_inside("faded blue", None), Ok(0));
+    }
+
+    #[test]
+    fn test_chains() {
+        let graph = BagGraph::parse(RULES).unwrap();
+
+        assert_eq!(
+            graph.shortest_chain("light red", "faded blue").unwrap(),
+            vec!["light red", "muted yellow", "faded blue"]
+        );
+        assert_eq!(
+            graph.longest_chain("light red", "faded blue").unwrap(),
+            vec![
+                "light red",
+                "bright white",
+                "shiny gold",
+                "dark olive",
+                "faded blue"
+            ]
+        );
+        assert_eq!(graph.shortest_chain("faded blue", "light red"), None);
+        assert_eq!(graph.longest_chain("faded blue", "light red"), None);
+    }
+
+    #[test]
+    fn test_undefined() {
+        let rules = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain no other bags.";
+
+        assert_eq!(
+            BagGraph::parse(rules).err().unwrap(),
+            GraphError::Undefined(vec![("light red".into(), "muted yellow".into())])
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        let rules = "light red bags contain 1 bright white bag.
+bright white bags contain 2 muted yellow bags, 1 faded blue bag.
+muted yellow bags contain 1 light red bag.
+faded blue bags contain no other bags.";
+        let err = BagGraph::parse(rules).err().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "Cycle: bright white -> muted yellow -> light red -> bright white"
+        );
+    }
+
+    #[test]
+    fn test_self_cycle() {
+        let rules = "light red bags contain 1 light red bag.";
+
+        assert_eq!(
+            BagGraph::parse(rules).err().unwrap(),
+            GraphError::Cycle(vec!["light red".into(), "light red".into()])
+        );
+    }
+
+    #[test]
+    fn test_deep_rules() {
+        let depth = 100_000;
+        let rules = (0..depth)
+            .map(|i| format!("dull c{} bags contain 1 dull c{} bag.", i, i + 1))
+            .chain(std::iter::once(format!(
+                "dull c{} bags contain no other bags.",
+                depth
+            )))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let graph = BagGraph::parse(&rules).unwrap();
+
+        assert_eq!(graph.total_inside("dull c0", None), Ok(depth));
+        assert_eq!(
+            graph.longest_chain("dull c0", "dull c10").unwrap().len(),
+            11
+        );
+        assert_eq!(graph.tree("dull c0", None).nodes.len(), depth + 1);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let depth = 70;
+        let rules = (0..depth)
+            .map(|i| format!("dull c{} bags contain 2 dull c{} bags.", i, i + 1))
+            .chain(std::iter::once(format!(
+                "dull c{} bags contain no other bags.",
+                depth
+            )))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let graph = BagGraph::parse(&rules).unwrap();
+
+        // 2 + 4 + ... + 2^n
+        assert_eq!(graph.total_inside("dull c60", None), Ok(2 * (1 << 10) - 2));
+        assert_eq!(
+            graph.total_inside("dull c0", Some(10)),
+            Ok(2 * (1 << 10) - 2)
+        );
+        assert_eq!(
+            graph.total_inside("dull c0", None),
+            Err(GraphError::Overflow("dull c0".into()))
+        );
+        assert_eq!(
+            graph.total_inside("dull c0", Some(depth)),
+            Err(GraphError::Overflow("dull c0".into()))
+        );
+    }
+
+    #[test]
+    fn test_tree() {
+        let graph = BagGraph::parse(RULES).unwrap();
+
+        assert_eq!(
+            graph.tree("shiny gold", Some(1)).to_string(),
+            "1 shiny gold\n  1 dark olive\n  2 vibrant plum\n"
+        );
+        assert_eq!(graph.tree("shiny gold", None).nodes.len(), 7);
+    }
+}
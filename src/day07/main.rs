@@ -1,9 +1,11 @@
-use advent2020::measure;
-use std::collections::{HashMap, HashSet};
-use std::fs;
+mod export;
+mod graph;
 
-type ContainRules<'a> = HashMap<&'a str, HashSet<&'a str>>;
-type NumberRules<'a> = HashMap<&'a str, HashSet<(&'a str, usize)>>;
+use advent2020::measure;
+use export::{Format, Scope};
+use graph::BagGraph;
+use std::error::Error;
+use std::{env, fs};
 
 fn parse_line(input: &str) -> Option<(&str, Vec<(&str, usize)>)> {
     let mut parts = input.split(" bags contain ");
@@ -33,114 +35,78 @@ fn parse_line(input: &str) -> Option<(&str, Vec<(&str, usize)>)> {
     Some((bag, inner))
 }
 
-fn parse(input: &str) -> Option<(ContainRules, NumberRules)> {
-    let mut contain = HashMap::new();
-    let mut number = HashMap::new();
-
-    for line in input.lines() {
-        let (bag, inners) = parse_line(line)?;
-
-        let mut contain_rule = HashSet::new();
-        let mut number_rule = HashSet::new();
-
-        for inner in inners.into_iter() {
-            contain_rule.insert(inner.0);
-            number_rule.insert(inner);
-        }
-
-        contain.insert(bag, contain_rule);
-        number.insert(bag, number_rule);
+fn print_chain(name: &str, chain: Option<Vec<&str>>) {
+    match chain {
+        Some(chain) => println!("{} chain: {}", name, chain.join(" -> ")),
+        None => println!("{} chain: not found", name),
     }
-
-    Some((contain, number))
 }
 
-fn solve_a(rules: &ContainRules, target: &str) -> usize {
-    let mut cache: HashMap<&str, bool> = HashMap::new();
-
-    rules
-        .keys()
-        .filter(|k| is_contain(k, target, rules, &mut cache))
-        .count()
-}
-
-fn is_contain<'a, 'b>(
-    cargo: &'a str,
-    target: &'a str,
-    rules: &'a ContainRules,
-    cache: &'b mut HashMap<&'a str, bool>,
-) -> bool {
-    if let Some(v) = cache.get(cargo) {
-        return *v;
-    }
-
-    match rules.get(cargo) {
-        None => false,
-        Some(rule) if rule.is_empty() => {
-            cache.insert(cargo, false);
-            false
-        }
-        Some(rule) if rule.contains(target) => {
-            cache.insert(cargo, true);
-            true
-        }
-        Some(rule) => {
-            let result = rule
-                .iter()
-                .filter(|n| is_contain(n, target, rules, cache))
-                .count()
-                > 0;
-
-            cache.insert(cargo, result);
-
-            result
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut target = "shiny gold".to_string();
+    let mut depth = None;
+    let mut chain = None;
+    let mut tree = false;
+    let mut format = None;
+    let mut from = None;
+    let mut to = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => target = args.next().ok_or("--target expects a colour")?,
+            "--depth" => depth = Some(args.next().ok_or("--depth expects a number")?.parse()?),
+            "--chain" => {
+                let from = args.next().ok_or("--chain expects two colours")?;
+                let to = args.next().ok_or("--chain expects two colours")?;
+                chain = Some((from, to));
+            }
+            "--tree" => tree = true,
+            "--dot" => format = Some(Format::Dot),
+            "--mermaid" => format = Some(Format::Mermaid),
+            "--from" => from = Some(args.next().ok_or("--from expects a colour")?),
+            "--to" => to = Some(args.next().ok_or("--to expects a colour")?),
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
         }
     }
-}
 
-fn solve_b(rules: &NumberRules, target: &str) -> usize {
-    let mut cache: HashMap<&str, usize> = HashMap::new();
-    calculate_b(rules, &target, &mut cache) - 1
-}
+    let data = fs::read_to_string("data/day07.txt")?;
 
-fn calculate_b<'a, 'b>(
-    rules: &'a NumberRules,
-    target: &'a str,
-    cache: &'b mut HashMap<&'a str, usize>,
-) -> usize {
-    if let Some(v) = cache.get(target) {
-        // dbg!(format!("founc cached {} -> {}", target.0, v));
-        return *v;
-    }
-
-    let rule = rules.get(target).unwrap();
-    // dbg!(&rule);
+    if let Some(format) = format {
+        let graph = BagGraph::parse(&data)?;
+        let scope = match (from.as_deref(), to.as_deref()) {
+            (Some(bag), None) => Scope::From(bag),
+            (None, Some(bag)) => Scope::To(bag),
+            (None, None) => Scope::All,
+            _ => return Err("use either --from or --to".into()),
+        };
 
-    let value: usize = rule
-        .iter()
-        .map(|inner| calculate_b(rules, &inner.0, cache) * inner.1)
-        .sum::<usize>()
-        + 1;
+        print!("{}", export::export(&graph, &scope, &format));
+        return Ok(());
+    }
 
-    // dbg!(format!("calculated {} -> {}", target.0, value));
+    let (result, elapsed) = measure(|| {
+        let graph = BagGraph::parse(&data)?;
 
-    cache.insert(target, value);
+        let a = graph.containers_of(&target, depth).len();
+        let b = graph.total_inside(&target, depth)?;
 
-    value
-}
+        Ok::<_, Box<dyn Error>>((graph, a, b))
+    });
+    let (graph, a, b) = result?;
 
-fn main() {
-    let ((a, b), elapsed) = measure(|| {
-        let data = fs::read_to_string("data/day07.txt").unwrap();
-        let (contain, numbers) = parse(&data).expect("cant parse input");
+    println!("task A: {}\ntask B: {}\nTotal time: {}μs ", a, b, elapsed);
 
-        let a = solve_a(&contain, "shiny gold");
-        let b = solve_b(&numbers, "shiny gold");
+    if let Some((from, to)) = chain {
+        print_chain("Shortest", graph.shortest_chain(&from, &to));
+        print_chain("Longest", graph.longest_chain(&from, &to));
+    }
 
-        (a, b)
-    });
+    if tree {
+        print!("{}", graph.tree(&target, depth));
+    }
 
-    println!("task A: {}\ntask B: {}\nTotal time: {}μs ", a, b, elapsed);
+    Ok(())
 }
 
 #[cfg(test)]
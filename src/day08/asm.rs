@@ -0,0 +1,215 @@
+use crate::isa::{self, Insruction, Operand, REGISTERS};
+use crate::Programm;
+use std::collections::{BTreeSet, HashMap};
+
+type Error = Box<dyn std::error::Error>;
+
+fn strip_comment(line: &str) -> &str {
+    match line.find([';', '#']) {
+        Some(start) => &line[..start],
+        None => line,
+    }
+}
+
+fn valid_label(label: &str) -> bool {
+    let mut chars = label.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && isa::register(label).is_none()
+}
+
+/// Assembles source with `label:` definitions, labels as jump operands and
+/// `;` or `#` comments. Plain boot code assembles as is.
+pub fn assemble(input: &str) -> Result<Programm, Error> {
+    let mut labels = HashMap::new();
+    let mut lines = vec![];
+
+    for (number, line) in input.lines().enumerate() {
+        let mut line = strip_comment(line).trim();
+
+        while let Some(end) = line.find(':') {
+            let label = line[..end].trim();
+            if !valid_label(label) {
+                return Err(format!("line {}: invalid label '{}'", number + 1, label).into());
+            }
+            if labels.insert(label, lines.len()).is_some() {
+                return Err(format!("line {}: duplicate label '{}'", number + 1, label).into());
+            }
+            line = line[end + 1..].trim();
+        }
+
+        if !line.is_empty() {
+            lines.push((number + 1, line));
+        }
+    }
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, (number, line))| {
+            let err = |msg: &str| format!("line {}: {}: {}", number, msg, line);
+
+            let (name, args) = isa::split(line);
+            let op = isa::opcode(name).ok_or_else(|| err("unknown opcode"))?;
+            if args.len() != op.operands.len() {
+                return Err(err(&format!("expected {} operands", op.operands.len())).into());
+            }
+
+            let values = op
+                .operands
+                .iter()
+                .zip(args)
+                .map(|(kind, arg)| {
+                    let value = match kind {
+                        Operand::Reg => isa::register(arg).map(|r| r as i32),
+                        Operand::Imm => isa::number(arg),
+                        Operand::Offset => isa::number(arg)
+                            .or_else(|| labels.get(arg).map(|t| *t as i32 - index as i32)),
+                    };
+
+                    value.ok_or_else(|| err(&format!("bad operand '{}'", arg)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(op.build(&values))
+        })
+        .collect()
+}
+
+/// Turns a program back into assembly, naming every jump target inside the
+/// program (or right past its end) with a label.
+pub fn disassemble(prog: &[Insruction]) -> String {
+    let target = |index: usize, ins: &Insruction| {
+        let target = index as i64 + i64::from(ins.offset()?);
+        if target >= 0 && target <= prog.len() as i64 {
+            Some(target as usize)
+        } else {
+            None
+        }
+    };
+
+    let targets = prog
+        .iter()
+        .enumerate()
+        .filter_map(|(index, ins)| target(index, ins))
+        .collect::<BTreeSet<_>>();
+
+    let mut out = String::new();
+    for (index, ins) in prog.iter().enumerate() {
+        if targets.contains(&index) {
+            out += &format!("L{}:\n", index);
+        }
+
+        let args = ins
+            .opcode()
+            .operands
+            .iter()
+            .zip(ins.args())
+            .map(|(kind, v)| match (kind, target(index, ins)) {
+                (Operand::Reg, _) => REGISTERS[v as usize].to_string(),
+                (Operand::Offset, Some(target)) => format!("L{}", target),
+                _ => format!("{:+}", v),
+            })
+            .collect::<Vec<_>>();
+
+        out += "    ";
+        out += ins.name();
+        if !args.is_empty() {
+            out += " ";
+            out += &args.join(", ");
+        }
+        out += "\n";
+    }
+
+    if targets.contains(&prog.len()) {
+        out += &format!("L{}:\n", prog.len());
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{StepResult, CPU};
+
+    const SOURCE: &str = "; counts b down, adding 2 to a every time
+    mov b, 5
+loop: acc +2
+    add b, -1
+    jnz b, loop   # back while b != 0
+    call done
+    hlt
+done:
+    acc +100
+    ret";
+
+    #[test]
+    fn test_assemble() {
+        let prog = assemble(SOURCE).unwrap();
+
+        assert_eq!(prog.len(), 8);
+        assert_eq!(prog[3], Insruction::Jnz(1, -2));
+        assert_eq!(prog[4], Insruction::Call(2));
+    }
+
+    #[test]
+    fn test_run() {
+        let prog = assemble(SOURCE).unwrap();
+        let mut cpu = CPU::new(&prog);
+        while cpu.step() == StepResult::Ok {}
+
+        assert_eq!(cpu.acc(), 110);
+        assert_eq!(cpu.regs[1], 0);
+        assert_eq!(cpu.current(), Some(&Insruction::Hlt));
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert!(assemble("foo +1").is_err());
+        assert!(assemble("jmp nowhere").is_err());
+        assert!(assemble("acc +1, +2").is_err());
+        assert!(assemble("x: nop +0\nx: nop +0").is_err());
+        assert!(assemble("b: nop +0").is_err());
+        assert!(assemble("mov e, 1").is_err());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let prog = assemble(SOURCE).unwrap();
+        let text = disassemble(&prog);
+
+        assert_eq!(
+            text,
+            "    mov b, +5
+L1:
+    acc +2
+    add b, -1
+    jnz b, L1
+    call L6
+    hlt
+L6:
+    acc +100
+    ret
+"
+        );
+        assert_eq!(assemble(&text).unwrap(), prog);
+    }
+
+    #[test]
+    fn test_roundtrip_input() {
+        let data = std::fs::read_to_string("data/day08.txt").unwrap();
+        let prog = assemble(&data).unwrap();
+
+        assert_eq!(assemble(&disassemble(&prog)).unwrap(), prog);
+    }
+
+    #[test]
+    fn test_roundtrip_outside() {
+        let prog = assemble("jmp -5\nnop +2\njmp +0").unwrap();
+        let text = disassemble(&prog);
+
+        assert_eq!(text, "    jmp -5\n    nop L3\nL2:\n    jmp L2\nL3:\n");
+        assert_eq!(assemble(&text).unwrap(), prog);
+    }
+}
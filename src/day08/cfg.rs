@@ -0,0 +1,376 @@
+use crate::isa::Insruction;
+
+/// Where control can go after an instruction.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Target {
+    Ins(usize),
+    /// Right past the last instruction, or a halt: the program terminates.
+    End,
+    /// A jump that leaves the program anywhere but its end.
+    Outside(i64),
+}
+
+impl Target {
+    pub fn of(len: usize, index: usize, offset: i32) -> Self {
+        let target = index as i64 + i64::from(offset);
+        if target == len as i64 {
+            Target::End
+        } else if target >= 0 && target < len as i64 {
+            Target::Ins(target as usize)
+        } else {
+            Target::Outside(target)
+        }
+    }
+
+    /// Node number, with the end of the program as node `len`.
+    pub fn node(&self, len: usize) -> Option<usize> {
+        match self {
+            Target::Ins(index) => Some(*index),
+            Target::End => Some(len),
+            Target::Outside(_) => None,
+        }
+    }
+}
+
+/// Statically possible successors of a single instruction.
+///
+/// Conditional jumps and calls have both their target and the next
+/// instruction (where a call returns to); `ret` is modelled as terminating,
+/// which is what it does with an empty call stack.
+pub fn successors(len: usize, index: usize, ins: &Insruction) -> Vec<Target> {
+    let next = Target::of(len, index, 1);
+
+    match ins {
+        Insruction::Noop(_) | Insruction::Acc(_) | Insruction::Mov(..) | Insruction::Add(..) => {
+            vec![next]
+        }
+        Insruction::Jmp(v) => vec![Target::of(len, index, *v)],
+        Insruction::Jz(_, v) | Insruction::Jnz(_, v) | Insruction::Call(v) => {
+            vec![Target::of(len, index, *v), next]
+        }
+        Insruction::Ret | Insruction::Hlt => vec![Target::End],
+    }
+}
+
+/// Whether control flow after this instruction is fixed, regardless of
+/// register values and the call stack.
+pub fn is_static(ins: &Insruction) -> bool {
+    !matches!(
+        ins,
+        Insruction::Jz(..) | Insruction::Jnz(..) | Insruction::Call(_) | Insruction::Ret
+    )
+}
+
+#[derive(Debug)]
+pub struct Cfg {
+    succ: Vec<Vec<Target>>,
+    pred: Vec<Vec<usize>>,
+}
+
+impl Cfg {
+    pub fn new(prog: &[Insruction]) -> Self {
+        let len = prog.len();
+        let succ: Vec<_> = prog
+            .iter()
+            .enumerate()
+            .map(|(index, ins)| successors(len, index, ins))
+            .collect();
+
+        let mut pred = vec![vec![]; len + 1];
+        for (index, targets) in succ.iter().enumerate() {
+            for node in targets.iter().filter_map(|t| t.node(len)) {
+                pred[node].push(index);
+            }
+        }
+
+        Self { succ, pred }
+    }
+
+    pub fn len(&self) -> usize {
+        self.succ.len()
+    }
+
+    pub fn successors(&self, index: usize) -> &[Target] {
+        &self.succ[index]
+    }
+
+    /// Instructions that can lead here, `len` meaning the end of the program.
+    pub fn predecessors(&self, node: usize) -> &[usize] {
+        &self.pred[node]
+    }
+
+    /// Marks every instruction from which the end of the program is reachable.
+    pub fn reaches_end(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.len() + 1];
+        let mut queue = vec![self.len()];
+        reached[self.len()] = true;
+
+        while let Some(node) = queue.pop() {
+            for &prev in self.predecessors(node) {
+                if !reached[prev] {
+                    reached[prev] = true;
+                    queue.push(prev);
+                }
+            }
+        }
+
+        reached.truncate(self.len());
+        reached
+    }
+
+    /// Marks every instruction execution can get to from the first one.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.len()];
+        let mut queue = vec![];
+        if !reached.is_empty() {
+            reached[0] = true;
+            queue.push(0);
+        }
+
+        while let Some(index) = queue.pop() {
+            for target in self.successors(index) {
+                if let Target::Ins(next) = *target {
+                    if !reached[next] {
+                        reached[next] = true;
+                        queue.push(next);
+                    }
+                }
+            }
+        }
+
+        reached
+    }
+
+    pub fn unreachable(&self) -> Vec<usize> {
+        let reached = self.reachable();
+        (0..self.len()).filter(|&index| !reached[index]).collect()
+    }
+
+    /// Jumps leaving the program anywhere but its end, with their targets.
+    pub fn outside(&self) -> Vec<(usize, i64)> {
+        (0..self.len())
+            .flat_map(|index| {
+                self.successors(index).iter().filter_map(move |t| match t {
+                    Target::Outside(target) => Some((index, *target)),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// Reachable cycles that can't get to the end of the program: once
+    /// execution enters one, it never terminates (short of a bad jump).
+    ///
+    /// These are the strongly connected components of the doomed part of the
+    /// graph, found Kosaraju-style with two explicit-stack passes.
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        let reachable = self.reachable();
+        let good = self.reaches_end();
+        let doomed = |index: usize| reachable[index] && !good[index];
+        let next = |index: usize| {
+            self.successors(index)
+                .iter()
+                .filter_map(|t| match t {
+                    Target::Ins(next) if doomed(*next) => Some(*next),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut order = vec![];
+        let mut visited = vec![false; self.len()];
+        for start in (0..self.len()).filter(|&i| doomed(i)) {
+            if visited[start] {
+                continue;
+            }
+
+            visited[start] = true;
+            let mut stack = vec![(start, next(start), 0)];
+            while let Some((index, targets, pos)) = stack.last_mut() {
+                match targets.get(*pos) {
+                    Some(&target) => {
+                        *pos += 1;
+                        if !visited[target] {
+                            visited[target] = true;
+                            stack.push((target, next(target), 0));
+                        }
+                    }
+                    None => {
+                        order.push(*index);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        let mut component = vec![None; self.len()];
+        let mut loops = vec![];
+        for &start in order.iter().rev() {
+            if component[start].is_some() {
+                continue;
+            }
+
+            let mut members = vec![start];
+            let mut queue = vec![start];
+            component[start] = Some(loops.len());
+            while let Some(index) = queue.pop() {
+                for &prev in self.predecessors(index) {
+                    if doomed(prev) && component[prev].is_none() {
+                        component[prev] = Some(loops.len());
+                        members.push(prev);
+                        queue.push(prev);
+                    }
+                }
+            }
+
+            members.sort_unstable();
+            loops.push(members);
+        }
+
+        loops.retain(|members| {
+            members.len() > 1
+                || self
+                    .successors(members[0])
+                    .contains(&Target::Ins(members[0]))
+        });
+        loops.sort();
+        loops
+    }
+}
+
+/// Renders the control-flow graph, with unreachable instructions dashed and
+/// infinite loops and bad jumps in red.
+pub fn dot(prog: &[Insruction]) -> String {
+    let cfg = Cfg::new(prog);
+    let reachable = cfg.reachable();
+    let looping = cfg.loops().concat();
+    let mut out = vec![
+        "digraph cfg {".to_string(),
+        "    node [shape=box];".to_string(),
+    ];
+
+    for (index, ins) in prog.iter().enumerate() {
+        let mut attrs = vec![format!("label=\"{}: {}\"", index, ins)];
+        if !reachable[index] {
+            attrs.push("style=dashed".to_string());
+        }
+        if looping.contains(&index) {
+            attrs.push("color=red".to_string());
+        }
+        out.push(format!("    n{} [{}];", index, attrs.join(", ")));
+    }
+    out.push("    end [shape=doublecircle];".to_string());
+
+    for (index, target) in cfg.outside() {
+        out.push(format!(
+            "    out{} [label=\"{}\", shape=octagon, color=red];",
+            index, target
+        ));
+    }
+
+    for index in 0..cfg.len() {
+        for target in cfg.successors(index) {
+            let node = match target {
+                Target::Ins(next) => format!("n{}", next),
+                Target::End => "end".to_string(),
+                Target::Outside(_) => format!("out{}", index),
+            };
+            out.push(format!("    n{} -> {};", index, node));
+        }
+    }
+
+    out.push("}".to_string());
+    out.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_targets() {
+        assert_eq!(Target::of(3, 1, 2), Target::End);
+        assert_eq!(Target::of(3, 1, -1), Target::Ins(0));
+        assert_eq!(Target::of(3, 1, -2), Target::Outside(-1));
+        assert_eq!(Target::of(3, 1, 5), Target::Outside(6));
+    }
+
+    #[test]
+    fn test_reaches_end() {
+        let prog = parse("nop +0\njmp +2\njmp -1\nacc +1\njmp -4").unwrap();
+        let cfg = Cfg::new(&prog);
+
+        assert_eq!(cfg.predecessors(5), &[] as &[usize]);
+        assert_eq!(cfg.reaches_end(), vec![false; 5]);
+
+        let prog = parse("nop +0\njmp +2\njmp -1\nacc +1\njz a, -4").unwrap();
+        let cfg = Cfg::new(&prog);
+
+        assert_eq!(cfg.predecessors(5), &[4]);
+        assert_eq!(cfg.reaches_end(), vec![true; 5]);
+    }
+
+    #[test]
+    fn test_analysis() {
+        let prog = parse(
+            "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6",
+        )
+        .unwrap();
+        let cfg = Cfg::new(&prog);
+
+        assert_eq!(cfg.unreachable(), vec![5, 8]);
+        assert_eq!(cfg.loops(), vec![vec![1, 2, 3, 4, 6, 7]]);
+        assert_eq!(cfg.outside(), vec![]);
+    }
+
+    #[test]
+    fn test_analysis_outside() {
+        let prog = parse(
+            "jmp +2
+jmp +0
+jz b, -3
+nop +9
+jmp +0",
+        )
+        .unwrap();
+        let cfg = Cfg::new(&prog);
+
+        assert_eq!(cfg.unreachable(), vec![1]);
+        assert_eq!(cfg.loops(), vec![vec![4]]);
+        assert_eq!(cfg.outside(), vec![(2, -1)]);
+    }
+
+    #[test]
+    fn test_dot() {
+        let prog = parse(
+            "jmp +2
+acc +1
+jnz a, -9",
+        )
+        .unwrap();
+        let expected = r#"digraph cfg {
+    node [shape=box];
+    n0 [label="0: jmp +2"];
+    n1 [label="1: acc +1", style=dashed];
+    n2 [label="2: jnz a, -9"];
+    end [shape=doublecircle];
+    out2 [label="-7", shape=octagon, color=red];
+    n0 -> n2;
+    n1 -> n2;
+    n2 -> out2;
+    n2 -> end;
+}
+"#;
+
+        assert_eq!(dot(&prog), expected);
+    }
+}
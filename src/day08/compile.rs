@@ -0,0 +1,272 @@
+use crate::cfg::{self, Target};
+use crate::isa::{Insruction, Reg, REGISTERS};
+use crate::{Fault, StepResult, CPU, MAX_STACK};
+
+type Regs = [i32; REGISTERS.len()];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Exit {
+    /// Ran past the last instruction or through a halt.
+    Stop,
+    Fault(Fault),
+    /// About to run an instruction for the second time. Only reported for
+    /// programs without conditional jumps and calls, where that proves the
+    /// program never terminates.
+    Loop,
+    /// Executed the allowed number of instructions.
+    Limit,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub exit: Exit,
+    pub ip: usize,
+    pub regs: Regs,
+}
+
+/// Runs `prog` on the reference `CPU`, with the same stop rules as
+/// `Compiled::run`.
+pub fn interpret(prog: &[Insruction], limit: usize) -> Outcome {
+    let once = prog.iter().all(cfg::is_static);
+    let mut visited = vec![false; prog.len()];
+    let mut executed = 0;
+    let mut cpu = CPU::new(prog);
+
+    loop {
+        let ip = cpu.ip();
+        let exit = if ip >= prog.len() {
+            None
+        } else if once && visited[ip] {
+            Some(Exit::Loop)
+        } else if executed == limit {
+            Some(Exit::Limit)
+        } else {
+            visited[ip] = true;
+            None
+        };
+
+        let exit = exit.or_else(|| match cpu.step() {
+            StepResult::Ok => None,
+            StepResult::Stop => Some(Exit::Stop),
+            StepResult::Fault(fault) => Some(Exit::Fault(fault)),
+        });
+
+        match exit {
+            Some(exit) => {
+                return Outcome {
+                    exit,
+                    ip: cpu.ip(),
+                    regs: cpu.regs,
+                }
+            }
+            None => executed += 1,
+        }
+    }
+}
+
+/// One compiled instruction. Jump targets are indices into the op list, which
+/// continues past the program with an `End` op and a `Fault` op for every
+/// jump leaving it, so the runner never has to bounds-check.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Op {
+    Next,
+    Acc(i32),
+    Goto(usize),
+    Mov(Reg, i32),
+    Add(Reg, i32),
+    Jz(Reg, usize),
+    Jnz(Reg, usize),
+    Call(usize),
+    Ret,
+    Halt,
+    End,
+    Fault { from: usize, target: i64 },
+}
+
+pub struct Compiled {
+    ops: Vec<Op>,
+    len: usize,
+    once: bool,
+}
+
+impl Compiled {
+    pub fn new(prog: &[Insruction]) -> Self {
+        let len = prog.len();
+        let mut faults = vec![];
+        let mut resolve = |index: usize, offset: i32| match Target::of(len, index, offset) {
+            Target::Ins(next) => next,
+            Target::End => len,
+            Target::Outside(target) => {
+                faults.push(Op::Fault {
+                    from: index,
+                    target,
+                });
+                len + faults.len()
+            }
+        };
+
+        let mut ops: Vec<Op> = prog
+            .iter()
+            .enumerate()
+            .map(|(index, ins)| match *ins {
+                Insruction::Noop(_) => Op::Next,
+                Insruction::Acc(v) => Op::Acc(v),
+                Insruction::Jmp(v) => Op::Goto(resolve(index, v)),
+                Insruction::Mov(r, v) => Op::Mov(r, v),
+                Insruction::Add(r, v) => Op::Add(r, v),
+                Insruction::Jz(r, v) => Op::Jz(r, resolve(index, v)),
+                Insruction::Jnz(r, v) => Op::Jnz(r, resolve(index, v)),
+                Insruction::Call(v) => Op::Call(resolve(index, v)),
+                Insruction::Ret => Op::Ret,
+                Insruction::Hlt => Op::Halt,
+            })
+            .collect();
+
+        ops.push(Op::End);
+        ops.extend(faults);
+
+        Self {
+            ops,
+            len,
+            once: prog.iter().all(cfg::is_static),
+        }
+    }
+
+    /// Runs at most `limit` instructions; the result matches `interpret`.
+    pub fn run(&self, limit: usize) -> Outcome {
+        let mut regs: Regs = [0; REGISTERS.len()];
+        let mut stack = vec![];
+        let mut visited = vec![false; if self.once { self.len } else { 0 }];
+        let mut executed = 0;
+        let mut pc = 0;
+
+        let outcome = |exit, ip, regs| Outcome { exit, ip, regs };
+
+        loop {
+            let op = self.ops[pc];
+            if pc < self.len {
+                if self.once {
+                    if visited[pc] {
+                        return outcome(Exit::Loop, pc, regs);
+                    }
+                    visited[pc] = true;
+                }
+                if executed == limit {
+                    return outcome(Exit::Limit, pc, regs);
+                }
+                executed += 1;
+            }
+
+            pc = match op {
+                Op::Next => pc + 1,
+                Op::Acc(v) => {
+                    regs[0] = regs[0].wrapping_add(v);
+                    pc + 1
+                }
+                Op::Goto(target) => target,
+                Op::Mov(r, v) => {
+                    regs[r] = v;
+                    pc + 1
+                }
+                Op::Add(r, v) => {
+                    regs[r] = regs[r].wrapping_add(v);
+                    pc + 1
+                }
+                Op::Jz(r, target) if regs[r] == 0 => target,
+                Op::Jnz(r, target) if regs[r] != 0 => target,
+                Op::Jz(..) | Op::Jnz(..) => pc + 1,
+                Op::Call(_) if stack.len() == MAX_STACK => {
+                    return outcome(Exit::Fault(Fault::StackOverflow), pc, regs)
+                }
+                Op::Call(target) => {
+                    stack.push(pc + 1);
+                    target
+                }
+                Op::Ret => match stack.pop() {
+                    Some(next) => next,
+                    None => return outcome(Exit::Stop, pc, regs),
+                },
+                Op::Halt => return outcome(Exit::Stop, pc, regs),
+                Op::End => return outcome(Exit::Stop, pc, regs),
+                Op::Fault { from, target } => {
+                    return outcome(Exit::Fault(Fault::Jump(target)), from, regs)
+                }
+            };
+        }
+    }
+}
+
+/// A program of `body` pseudo-random `nop`/`acc`/forward `jmp` instructions,
+/// run `rounds` times by a counter in register `b`.
+pub fn sample(body: usize, rounds: i32, seed: u64) -> Vec<Insruction> {
+    let mut state = seed;
+    let mut random = move |n: u64| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((state >> 33) % n) as i32
+    };
+
+    let mut prog = vec![Insruction::Mov(1, rounds)];
+    for index in 0..body {
+        let ins = match random(3) {
+            0 => Insruction::Noop(random(100) - 50),
+            1 => Insruction::Acc(random(100) - 50),
+            _ => Insruction::Jmp((random(3) + 1).min((body - index) as i32)),
+        };
+        prog.push(ins);
+    }
+    prog.push(Insruction::Add(1, -1));
+    prog.push(Insruction::Jnz(1, -(body as i32) - 1));
+    prog.push(Insruction::Hlt);
+
+    prog
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    fn check(source: &str, limit: usize, exit: Exit) {
+        let prog = parse(source).unwrap();
+        let outcome = interpret(&prog, limit);
+
+        assert_eq!(outcome.exit, exit);
+        assert_eq!(Compiled::new(&prog).run(limit), outcome);
+    }
+
+    #[test]
+    fn test_same_exits() {
+        check("nop +0\nacc +1\njmp -1", 100, Exit::Loop);
+        check("acc +1\njmp +2\nacc +5", 100, Exit::Stop);
+        check("acc +1\njmp -2", 100, Exit::Fault(Fault::Jump(-1)));
+        check("jz b, +3\nacc +1\njnz a, +5", 100, Exit::Stop);
+        check(
+            "mov b, +1\ncall +3\nadd b, -1\njnz b, -2\nret",
+            100,
+            Exit::Stop,
+        );
+        check("call +2\nhlt\nacc +7\nret", 100, Exit::Stop);
+        check("acc +1\njnz a, -1", 10, Exit::Limit);
+        check("call +0", 10_000, Exit::Fault(Fault::StackOverflow));
+        check("acc +2147483647\nacc +1", 100, Exit::Stop);
+    }
+
+    #[test]
+    fn test_same_input() {
+        check(include_str!("../../data/day08.txt"), 1000, Exit::Loop);
+    }
+
+    #[test]
+    fn test_same_sample() {
+        for seed in 0..10 {
+            let prog = sample(200, 20, seed);
+            let outcome = interpret(&prog, usize::MAX);
+
+            assert_eq!(outcome.exit, Exit::Stop);
+            assert_eq!(Compiled::new(&prog).run(usize::MAX), outcome);
+            assert_eq!(Compiled::new(&prog).run(777), interpret(&prog, 777));
+        }
+    }
+}
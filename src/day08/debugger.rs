@@ -0,0 +1,340 @@
+use crate::{Insruction, StepResult, CPU};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, PartialEq)]
+pub enum Breakpoint {
+    Index(usize),
+    Opcode(String),
+}
+
+impl Breakpoint {
+    fn parse(input: &str) -> Self {
+        match input.parse() {
+            Ok(index) => Breakpoint::Index(index),
+            Err(_) => Breakpoint::Opcode(input.to_string()),
+        }
+    }
+
+    fn matches(&self, ip: usize, ins: &Insruction) -> bool {
+        match self {
+            Breakpoint::Index(index) => *index == ip,
+            Breakpoint::Opcode(name) => ins.name() == name,
+        }
+    }
+}
+
+/// Steps `c` runs before handing control back.
+const BUDGET: usize = 1_000_000;
+
+enum Control {
+    Continue,
+    Quit,
+}
+
+const HELP: &str = "commands:
+  b <index|opcode>  add breakpoint
+  d <index|opcode>  delete breakpoint
+  s [n]             step n instructions (default 1)
+  c                 continue until breakpoint, loop or stop (at most 1000000 steps)
+  w                 toggle watch on acc
+  t                 toggle execution trace
+  p                 print state
+  l                 show the instruction pointer cycle
+  q                 quit";
+
+pub struct Debugger<'a> {
+    cpu: CPU<'a>,
+    breakpoints: Vec<Breakpoint>,
+    watch: bool,
+    trace: bool,
+    // ip of every executed step, and the step at which each ip was first
+    // seen; only kept when a repeated ip means a loop (`CPU::once`)
+    history: Vec<usize>,
+    first_seen: HashMap<usize, usize>,
+    cycle: Option<Vec<usize>>,
+    stopped: bool,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(prog: &'a [Insruction]) -> Self {
+        Self {
+            cpu: CPU::new(prog),
+            breakpoints: vec![],
+            watch: false,
+            trace: false,
+            history: vec![],
+            first_seen: HashMap::new(),
+            cycle: None,
+            stopped: false,
+        }
+    }
+
+    /// Reads commands line by line, printing a prompt before each when `prompt` is set.
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        out: &mut W,
+        prompt: bool,
+    ) -> io::Result<()> {
+        let mut lines = input.lines();
+
+        loop {
+            if prompt {
+                write!(out, "(dbg) ")?;
+                out.flush()?;
+            }
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+
+            if let Control::Quit = self.execute(line.trim(), out)? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn execute<W: Write>(&mut self, command: &str, out: &mut W) -> io::Result<Control> {
+        let mut parts = command.split_whitespace();
+
+        match (parts.next(), parts.next()) {
+            (None, _) => {}
+            (Some("b"), Some(arg)) => {
+                self.breakpoints.push(Breakpoint::parse(arg));
+                writeln!(out, "breakpoint at {}", arg)?;
+            }
+            (Some("d"), Some(arg)) => {
+                let bp = Breakpoint::parse(arg);
+                self.breakpoints.retain(|b| *b != bp);
+            }
+            (Some("s"), n) => {
+                let n = n.and_then(|n| n.parse().ok()).unwrap_or(1);
+                for _ in 0..n {
+                    if !self.step(out)? {
+                        break;
+                    }
+                }
+                self.print_state(out)?;
+            }
+            (Some("c"), _) => {
+                let mut steps = 0;
+                while self.step(out)? {
+                    steps += 1;
+                    if let Some(ins) = self.cpu.current() {
+                        let ip = self.cpu.ip();
+                        if self.breakpoints.iter().any(|b| b.matches(ip, ins)) {
+                            writeln!(out, "breakpoint hit at {}", ip)?;
+                            break;
+                        }
+                    }
+                    if steps == BUDGET {
+                        writeln!(out, "still running after {} steps", steps)?;
+                        break;
+                    }
+                }
+                self.print_state(out)?;
+            }
+            (Some("w"), _) => {
+                self.watch = !self.watch;
+                writeln!(out, "watch acc: {}", self.watch)?;
+            }
+            (Some("t"), _) => {
+                self.trace = !self.trace;
+                writeln!(out, "trace: {}", self.trace)?;
+            }
+            (Some("p"), _) => self.print_state(out)?,
+            (Some("l"), _) => match &self.cycle {
+                Some(cycle) => {
+                    let ips = cycle.iter().map(|ip| ip.to_string()).collect::<Vec<_>>();
+                    writeln!(out, "loop: {} -> {}", ips.join(" -> "), cycle[0])?;
+                }
+                None => writeln!(out, "no loop detected yet")?,
+            },
+            (Some("q"), _) => return Ok(Control::Quit),
+            _ => writeln!(out, "{}", HELP)?,
+        }
+
+        Ok(Control::Continue)
+    }
+
+    /// Executes one instruction; false when execution can't go on.
+    fn step<W: Write>(&mut self, out: &mut W) -> io::Result<bool> {
+        if self.stopped {
+            writeln!(out, "program terminated")?;
+            return Ok(false);
+        }
+
+        let ip = self.cpu.ip();
+        if let Some(first) = self.first_seen.get(&ip) {
+            if self.cycle.is_none() {
+                self.cycle = Some(self.history[*first..].to_vec());
+            }
+            writeln!(out, "loop detected: {} is about to run again", ip)?;
+            return Ok(false);
+        }
+
+        let ins = self.cpu.current().cloned();
+        let acc = self.cpu.acc();
+
+        match self.cpu.step() {
+            StepResult::Ok => {}
+            StepResult::Stop => {
+                self.stopped = true;
+                writeln!(out, "program terminated at {}, acc = {}", ip, acc)?;
+                return Ok(false);
+            }
+            StepResult::Fault(fault) => {
+                self.stopped = true;
+                writeln!(out, "fault at {}: {}", ip, fault)?;
+                return Ok(false);
+            }
+        }
+
+        if self.cpu.once {
+            self.first_seen.insert(ip, self.history.len());
+            self.history.push(ip);
+        }
+
+        if self.trace {
+            if let Some(ins) = ins {
+                let ins = ins.to_string();
+                writeln!(out, "{:>5}: {:<10} acc = {}", ip, ins, self.cpu.acc())?;
+            }
+        }
+
+        if self.watch && acc != self.cpu.acc() {
+            writeln!(out, "acc: {} -> {} at {}", acc, self.cpu.acc(), ip)?;
+        }
+
+        Ok(true)
+    }
+
+    fn print_state<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let ip = self.cpu.ip();
+        match self.cpu.current() {
+            Some(ins) => writeln!(out, "ip = {} ({}), acc = {}", ip, ins, self.cpu.acc()),
+            None => writeln!(out, "ip = {} (end), acc = {}", ip, self.cpu.acc()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    const PROG: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+    fn session(script: &str) -> String {
+        let prog = parse(PROG).unwrap();
+        let mut out = vec![];
+        Debugger::new(&prog)
+            .run(script.as_bytes(), &mut out, false)
+            .unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_step() {
+        assert_eq!(session("s 2"), "ip = 2 (jmp +4), acc = 1\n");
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let out = session("b 4\nc\nd 4\nb acc\nc\nc");
+        assert_eq!(
+            out,
+            "breakpoint at 4
+breakpoint hit at 4
+ip = 4 (jmp -3), acc = 5
+breakpoint at acc
+breakpoint hit at 1
+ip = 1 (acc +1), acc = 5
+loop detected: 1 is about to run again
+ip = 1 (acc +1), acc = 5
+"
+        );
+    }
+
+    #[test]
+    fn test_loop_report() {
+        let out = session("w\nc\nl");
+        assert_eq!(
+            out,
+            "watch acc: true
+acc: 0 -> 1 at 1
+acc: 1 -> 2 at 6
+acc: 2 -> 5 at 3
+loop detected: 1 is about to run again
+ip = 1 (acc +1), acc = 5
+loop: 1 -> 2 -> 6 -> 7 -> 3 -> 4 -> 1
+"
+        );
+    }
+
+    #[test]
+    fn test_conditional_code() {
+        let prog = parse("mov b, +2\ncall +4\nadd b, -1\njnz b, -2\nhlt\nacc +1\nret").unwrap();
+        let mut out = vec![];
+        Debugger::new(&prog)
+            .run("c\nl".as_bytes(), &mut out, false)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "program terminated at 4, acc = 2
+ip = 4 (hlt), acc = 2
+no loop detected yet
+"
+        );
+    }
+
+    #[test]
+    fn test_budget() {
+        let prog = parse("add b, +1\njnz b, -1").unwrap();
+        let mut debugger = Debugger::new(&prog);
+        let mut out = vec![];
+        debugger.run("c\nl".as_bytes(), &mut out, false).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "still running after 1000000 steps
+ip = 0 (add b, +1), acc = 0
+no loop detected yet
+"
+        );
+        assert!(debugger.history.is_empty());
+    }
+
+    #[test]
+    fn test_trace_to_end() {
+        let prog = parse("acc +2\njmp +1").unwrap();
+        let mut out = vec![];
+        Debugger::new(&prog)
+            .run("t\nc\ns".as_bytes(), &mut out, false)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "trace: true
+    0: acc +2     acc = 2
+    1: jmp +1     acc = 2
+program terminated at 2, acc = 2
+ip = 2 (end), acc = 2
+program terminated
+ip = 2 (end), acc = 2
+"
+        );
+    }
+}
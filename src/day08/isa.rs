@@ -0,0 +1,243 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::convert::TryFrom;
+use std::fmt;
+
+type Error = Box<dyn std::error::Error>;
+
+/// Register names; `a` is the accumulator the original boot code works with.
+pub const REGISTERS: [&str; 4] = ["a", "b", "c", "d"];
+
+pub type Reg = usize;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Insruction {
+    Noop(i32),
+    Acc(i32),
+    Jmp(i32),
+    Mov(Reg, i32),
+    Add(Reg, i32),
+    Jz(Reg, i32),
+    Jnz(Reg, i32),
+    Call(i32),
+    Ret,
+    Hlt,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Operand {
+    Reg,
+    Imm,
+    /// Jump distance relative to the instruction, a label in assembly.
+    Offset,
+}
+
+pub struct Opcode {
+    pub name: &'static str,
+    pub operands: &'static [Operand],
+    build: fn(&[i32]) -> Insruction,
+}
+
+impl Opcode {
+    pub fn build(&self, args: &[i32]) -> Insruction {
+        (self.build)(args)
+    }
+}
+
+/// Every opcode the VM knows, in the order of the `Insruction` variants.
+/// Adding one means a variant in `Insruction`, an entry here at the same
+/// position, and an arm in each of `Insruction::opcode`, `Insruction::args`,
+/// `CPU::step`, `cfg::successors` and `cfg::is_static`, plus a `compile::Op`
+/// built in `Compiled::new` and run in `Compiled::run`.
+pub static OPCODES: &[Opcode] = &[
+    Opcode {
+        name: "nop",
+        operands: &[Operand::Offset],
+        build: |a| Insruction::Noop(a[0]),
+    },
+    Opcode {
+        name: "acc",
+        operands: &[Operand::Imm],
+        build: |a| Insruction::Acc(a[0]),
+    },
+    Opcode {
+        name: "jmp",
+        operands: &[Operand::Offset],
+        build: |a| Insruction::Jmp(a[0]),
+    },
+    Opcode {
+        name: "mov",
+        operands: &[Operand::Reg, Operand::Imm],
+        build: |a| Insruction::Mov(a[0] as Reg, a[1]),
+    },
+    Opcode {
+        name: "add",
+        operands: &[Operand::Reg, Operand::Imm],
+        build: |a| Insruction::Add(a[0] as Reg, a[1]),
+    },
+    Opcode {
+        name: "jz",
+        operands: &[Operand::Reg, Operand::Offset],
+        build: |a| Insruction::Jz(a[0] as Reg, a[1]),
+    },
+    Opcode {
+        name: "jnz",
+        operands: &[Operand::Reg, Operand::Offset],
+        build: |a| Insruction::Jnz(a[0] as Reg, a[1]),
+    },
+    Opcode {
+        name: "call",
+        operands: &[Operand::Offset],
+        build: |a| Insruction::Call(a[0]),
+    },
+    Opcode {
+        name: "ret",
+        operands: &[],
+        build: |_| Insruction::Ret,
+    },
+    Opcode {
+        name: "hlt",
+        operands: &[],
+        build: |_| Insruction::Hlt,
+    },
+];
+
+pub fn opcode(name: &str) -> Option<&'static Opcode> {
+    OPCODES.iter().find(|op| op.name == name)
+}
+
+pub fn register(name: &str) -> Option<Reg> {
+    REGISTERS.iter().position(|r| *r == name)
+}
+
+/// Numbers may come without a sign, the original boot code always has one.
+pub fn number(input: &str) -> Option<i32> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^[+-]?\d+$").unwrap();
+    }
+
+    if RE.is_match(input) {
+        input.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Splits `op a, b` into the mnemonic and its operands.
+pub fn split(input: &str) -> (&str, Vec<&str>) {
+    let input = input.trim();
+    match input.find(char::is_whitespace) {
+        Some(end) => {
+            let args = input[end..]
+                .split(',')
+                .flat_map(|a| a.split_whitespace())
+                .collect();
+            (&input[..end], args)
+        }
+        None => (input, vec![]),
+    }
+}
+
+impl Insruction {
+    pub fn opcode(&self) -> &'static Opcode {
+        let index = match self {
+            Insruction::Noop(_) => 0,
+            Insruction::Acc(_) => 1,
+            Insruction::Jmp(_) => 2,
+            Insruction::Mov(..) => 3,
+            Insruction::Add(..) => 4,
+            Insruction::Jz(..) => 5,
+            Insruction::Jnz(..) => 6,
+            Insruction::Call(_) => 7,
+            Insruction::Ret => 8,
+            Insruction::Hlt => 9,
+        };
+
+        &OPCODES[index]
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.opcode().name
+    }
+
+    /// Operand values in registry order, registers as their index.
+    pub fn args(&self) -> Vec<i32> {
+        match self {
+            Insruction::Noop(v) | Insruction::Acc(v) | Insruction::Jmp(v) | Insruction::Call(v) => {
+                vec![*v]
+            }
+            Insruction::Mov(r, v)
+            | Insruction::Add(r, v)
+            | Insruction::Jz(r, v)
+            | Insruction::Jnz(r, v) => vec![*r as i32, *v],
+            Insruction::Ret | Insruction::Hlt => vec![],
+        }
+    }
+
+    /// Relative distance of the jump target, for instructions that have one.
+    pub fn offset(&self) -> Option<i32> {
+        self.opcode()
+            .operands
+            .iter()
+            .zip(self.args())
+            .find(|(kind, _)| **kind == Operand::Offset)
+            .map(|(_, v)| v)
+    }
+
+    /// The boot-code corruption fix: `nop` and `jmp` swapped, operand kept.
+    pub fn flipped(&self) -> Option<Insruction> {
+        match self {
+            Insruction::Noop(v) => Some(Insruction::Jmp(*v)),
+            Insruction::Jmp(v) => Some(Insruction::Noop(*v)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Insruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args = self
+            .opcode()
+            .operands
+            .iter()
+            .zip(self.args())
+            .map(|(kind, v)| match kind {
+                Operand::Reg => REGISTERS[v as usize].to_string(),
+                _ => format!("{:+}", v),
+            })
+            .collect::<Vec<_>>();
+
+        if args.is_empty() {
+            write!(f, "{}", self.name())
+        } else {
+            write!(f, "{} {}", self.name(), args.join(", "))
+        }
+    }
+}
+
+impl TryFrom<&str> for Insruction {
+    type Error = Error;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let err = || format!("invald input: {}", input);
+
+        let (name, args) = split(input);
+        let op = opcode(name).ok_or_else(err)?;
+        if args.len() != op.operands.len() {
+            return Err(err().into());
+        }
+
+        let values = op
+            .operands
+            .iter()
+            .zip(args)
+            .map(|(kind, arg)| match kind {
+                Operand::Reg => register(arg).map(|r| r as i32),
+                _ => number(arg),
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(err)?;
+
+        Ok(op.build(&values))
+    }
+}
@@ -1,70 +1,80 @@
+mod asm;
+mod cfg;
+mod compile;
+mod debugger;
+mod isa;
+mod repair;
+
 use advent2020::measure;
-use lazy_static::lazy_static;
-use regex::Regex;
+use cfg::{Cfg, Target};
+use compile::Exit;
+use debugger::Debugger;
+use isa::{Insruction, REGISTERS};
 use std::collections::HashSet;
-use std::convert::TryFrom;
-use std::mem;
+use std::fmt;
+use std::io::{self, BufReader};
+use std::{env, fs};
 
 type Error = Box<dyn std::error::Error>;
 
-#[derive(Debug, PartialEq, Clone)]
-enum Insruction {
-    Noop(i32),
-    Acc(i32),
-    Jmp(i32),
-}
-
-impl TryFrom<&str> for Insruction {
-    type Error = Error;
+type Programm = Vec<Insruction>;
 
-    fn try_from(input: &str) -> Result<Self, Self::Error> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r"^(\w+)\s+([+-]\d+)$").unwrap();
-        }
+fn parse(input: &str) -> Result<Programm, Error> {
+    asm::assemble(input)
+}
 
-        let err = || format!("invald input: {}", input);
+/// Deepest call nesting before a `call` faults.
+const MAX_STACK: usize = 1024;
 
-        let captures = RE.captures(input).ok_or_else(err)?;
-        let op = captures.get(1).map(|c| c.as_str()).ok_or_else(err)?;
-        let value = captures
-            .get(2)
-            .map(|c| c.as_str())
-            .and_then(|c| c.parse::<i32>().ok())
-            .ok_or_else(err)?;
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Fault {
+    /// A jump tried to leave the program anywhere but right past its end.
+    Jump(i64),
+    /// A call with `MAX_STACK` return addresses already on the stack.
+    StackOverflow,
+}
 
-        match (op, value) {
-            ("nop", v) => Ok(Insruction::Noop(v)),
-            ("acc", v) => Ok(Insruction::Acc(v)),
-            ("jmp", v) => Ok(Insruction::Jmp(v)),
-            (_, _) => Err(err().into()),
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::Jump(target) => write!(f, "jump outside the program to {}", target),
+            Fault::StackOverflow => write!(f, "call stack deeper than {}", MAX_STACK),
         }
     }
 }
 
-type Programm = Vec<Insruction>;
-
-fn parse(input: &str) -> Result<Programm, Error> {
-    input.lines().map(Insruction::try_from).collect()
-}
-
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 enum StepResult {
     Ok,
     Stop,
+    Fault(Fault),
 }
 
+/// Instruction pointer, registers and call stack.
+type State = (usize, [i32; REGISTERS.len()], Vec<usize>);
+
+#[allow(clippy::upper_case_acronyms)]
 struct CPU<'a> {
     prog: &'a [Insruction],
+    /// Control flow never depends on registers or the stack.
+    once: bool,
     ip: usize,
-    acc: i32,
+    regs: [i32; REGISTERS.len()],
+    stack: Vec<usize>,
+    halted: bool,
+    fault: Option<Fault>,
 }
 
 impl<'a> CPU<'a> {
     pub fn new(prog: &'a [Insruction]) -> Self {
         Self {
             prog,
+            once: prog.iter().all(cfg::is_static),
             ip: 0,
-            acc: 0,
+            regs: [0; REGISTERS.len()],
+            stack: vec![],
+            halted: false,
+            fault: None,
         }
     }
 
@@ -73,75 +83,89 @@ impl<'a> CPU<'a> {
     }
 
     pub fn acc(&self) -> i32 {
-        self.acc
+        self.regs[0]
     }
 
+    /// What has to come back for execution to be stuck in a loop: the whole
+    /// machine state, or just `ip` for programs of only `nop`, `acc` and
+    /// `jmp`, which take the same path whatever the registers hold.
+    pub fn state(&self) -> State {
+        if self.once {
+            (self.ip, [0; REGISTERS.len()], vec![])
+        } else {
+            (self.ip, self.regs, self.stack.clone())
+        }
+    }
+
+    pub fn current(&self) -> Option<&Insruction> {
+        self.prog.get(self.ip)
+    }
+
+    fn jump(&mut self, offset: i32) {
+        match Target::of(self.prog.len(), self.ip, offset) {
+            Target::Ins(ip) => self.ip = ip,
+            Target::End => self.ip = self.prog.len(),
+            Target::Outside(target) => self.fault = Some(Fault::Jump(target)),
+        }
+    }
+
+    /// Executes one instruction. The program terminates by running right past
+    /// its last instruction or through a halt; a jump anywhere else, or a call
+    /// nested too deep, faults and leaves `ip` on the instruction. Arithmetic
+    /// wraps around.
     pub fn step(&mut self) -> StepResult {
+        if let Some(fault) = self.fault {
+            return StepResult::Fault(fault);
+        }
+        if self.halted {
+            return StepResult::Stop;
+        }
+
         match self.prog.get(self.ip) {
-            Some(Insruction::Noop(_)) => {
+            Some(Insruction::Noop(_)) => self.ip += 1,
+            Some(Insruction::Acc(v)) => {
                 self.ip += 1;
-                StepResult::Ok
+                self.regs[0] = self.regs[0].wrapping_add(*v);
             }
-            Some(Insruction::Acc(v)) => {
+            Some(Insruction::Jmp(v)) => self.jump(*v),
+            Some(Insruction::Mov(r, v)) => {
                 self.ip += 1;
-                self.acc += v;
-                StepResult::Ok
+                self.regs[*r] = *v;
             }
-            Some(Insruction::Jmp(v)) => {
-                let next = self.ip as i32 + v;
-                self.ip = next as usize;
-                StepResult::Ok
+            Some(Insruction::Add(r, v)) => {
+                self.ip += 1;
+                self.regs[*r] = self.regs[*r].wrapping_add(*v);
             }
-            None => StepResult::Stop,
-        }
-    }
-}
-
-struct ProgMutator {
-    prog: Programm,
-    next: usize,
-}
-
-impl Iterator for ProgMutator {
-    type Item = Programm;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.prog.get(self.next) {
-                Some(Insruction::Noop(v)) => {
-                    let mut clone = self.prog.clone();
-                    let mut replace = Insruction::Jmp(*v);
-                    mem::swap(&mut clone[self.next], &mut replace);
-                    self.next += 1;
-
-                    return Some(clone);
-                }
-                Some(Insruction::Jmp(v)) => {
-                    let mut clone = self.prog.clone();
-                    let mut replace = Insruction::Noop(*v);
-                    mem::swap(&mut clone[self.next], &mut replace);
-                    self.next += 1;
-
-                    return Some(clone);
-                }
-                Some(_) => {}
-                _ => return None,
+            Some(Insruction::Jz(r, v)) if self.regs[*r] == 0 => self.jump(*v),
+            Some(Insruction::Jnz(r, v)) if self.regs[*r] != 0 => self.jump(*v),
+            Some(Insruction::Jz(..)) | Some(Insruction::Jnz(..)) => self.ip += 1,
+            Some(Insruction::Call(_)) if self.stack.len() == MAX_STACK => {
+                self.fault = Some(Fault::StackOverflow)
             }
-
-            self.next += 1;
+            Some(Insruction::Call(v)) => {
+                self.stack.push(self.ip + 1);
+                self.jump(*v);
+            }
+            Some(Insruction::Ret) => match self.stack.pop() {
+                Some(ip) => self.ip = ip,
+                None => self.halted = true,
+            },
+            Some(Insruction::Hlt) => self.halted = true,
+            None => self.halted = true,
         }
-    }
-}
 
-impl ProgMutator {
-    pub fn new(prog: Programm) -> Self {
-        Self { prog, next: 0 }
+        match self.fault {
+            Some(fault) => StepResult::Fault(fault),
+            None if self.halted => StepResult::Stop,
+            None => StepResult::Ok,
+        }
     }
 }
 
 enum RunResult {
     Loop,
     Stop,
+    Fault,
 }
 
 struct Solver {}
@@ -160,37 +184,131 @@ impl Solver {
     }
 
     pub fn solve_b(&self, prog: Programm) -> Option<i32> {
-        ProgMutator::new(prog).find_map(|prog| {
-            let mut cpu = CPU::new(&prog);
-            let result = self.run_till_stop(&mut cpu);
-            match result {
-                RunResult::Stop => Some(cpu.acc()),
-                _ => None,
-            }
-        })
+        repair::repair(&prog).ok()?.map(|fix| fix.acc)
     }
 
     fn run_till_stop(&self, cpu: &mut CPU) -> RunResult {
         let mut visited = HashSet::new();
 
         loop {
-            let step_result = cpu.step();
-            if step_result == StepResult::Stop {
-                return RunResult::Stop;
+            match cpu.step() {
+                StepResult::Stop => return RunResult::Stop,
+                StepResult::Fault(_) => return RunResult::Fault,
+                StepResult::Ok => {}
             }
 
-            if visited.contains(&cpu.ip()) {
+            if !visited.insert(cpu.state()) {
                 return RunResult::Loop;
             }
-
-            visited.insert(cpu.ip());
         }
     }
 }
 
-fn main() {
+fn debug(programm: &[Insruction], script: Option<String>) -> Result<(), Error> {
+    let mut debugger = Debugger::new(programm);
+    let mut out = io::stdout();
+
+    match script {
+        Some(path) => debugger.run(BufReader::new(fs::File::open(path)?), &mut out, false)?,
+        None => debugger.run(io::stdin().lock(), &mut out, true)?,
+    }
+
+    Ok(())
+}
+
+/// Steps `--run` executes before giving up on a program halting.
+const RUN_LIMIT: usize = 10_000_000;
+
+/// Runs an assembled program until it halts or faults, for at most `limit`
+/// steps. Programs of only `nop`, `acc` and `jmp` stop as soon as an
+/// instruction comes round again, since then they never halt.
+fn run(programm: &[Insruction], limit: usize) {
+    let outcome = compile::interpret(programm, limit);
+
+    match outcome.exit {
+        Exit::Stop => {}
+        Exit::Fault(fault) => println!("fault at {}: {}", outcome.ip, fault),
+        Exit::Loop => println!("loop detected at {}", outcome.ip),
+        Exit::Limit => println!("no halt within {} steps", limit),
+    }
+
+    for (name, value) in REGISTERS.iter().zip(outcome.regs.iter()) {
+        println!("{}: {}", name, value);
+    }
+}
+
+fn analyse(programm: &[Insruction]) {
+    let cfg = Cfg::new(programm);
+
+    println!("unreachable: {:?}", cfg.unreachable());
+    for members in cfg.loops() {
+        println!("infinite loop: {:?}", members);
+    }
+    for (index, target) in cfg.outside() {
+        println!("jump from {} outside the program to {}", index, target);
+    }
+}
+
+/// Times the reference CPU against compiled code on a generated program.
+fn bench(body: usize, rounds: i32) {
+    let programm = compile::sample(body, rounds, 8);
+
+    let (expected, interpreted) = measure(|| compile::interpret(&programm, usize::MAX));
+    let (compiled, elapsed) = measure(|| compile::Compiled::new(&programm));
+    let (outcome, run) = measure(|| compiled.run(usize::MAX));
+    assert_eq!(outcome, expected);
+
+    println!(
+        "{} instructions, {} rounds, acc = {}",
+        body, rounds, outcome.regs[0]
+    );
+    println!("Interpreter: {}μs", interpreted);
+    println!("Compile: {}μs, run: {}μs", elapsed, run);
+}
+
+fn main() -> Result<(), Error> {
+    let data = fs::read_to_string("data/day08.txt")?;
+
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("--debug") => return debug(&parse(&data)?, args.next()),
+        Some("--run") => {
+            let path = args.next().ok_or("--run expects a program file")?;
+            let limit = args.next().map_or(Ok(RUN_LIMIT), |v| v.parse())?;
+            run(&parse(&fs::read_to_string(path)?)?, limit);
+            return Ok(());
+        }
+        Some("--bench") => {
+            let body = args.next().map_or(Ok(10_000), |v| v.parse())?;
+            let rounds = args.next().map_or(Ok(1_000), |v| v.parse())?;
+            bench(body, rounds);
+            return Ok(());
+        }
+        Some("--repair") => {
+            let k = args.next().map_or(Ok(1), |k| k.parse())?;
+            match repair::repair_within(&parse(&data)?, k)? {
+                Some(fix) => println!("flip {:?}, acc {}", fix.flips, fix.acc),
+                None => println!("no repair with up to {} flips", k),
+            }
+            return Ok(());
+        }
+        Some(flag @ "--disasm") | Some(flag @ "--analyse") | Some(flag @ "--cfg") => {
+            let source = match args.next() {
+                Some(path) => fs::read_to_string(path)?,
+                None => data,
+            };
+            let programm = parse(&source)?;
+            match flag {
+                "--disasm" => print!("{}", asm::disassemble(&programm)),
+                "--analyse" => analyse(&programm),
+                _ => print!("{}", cfg::dot(&programm)),
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
     let ((a, b), elapsed) = measure(|| {
-        let data = std::fs::read_to_string("data/day08.txt").unwrap();
         let programm = parse(&data).unwrap();
 
         (
@@ -209,17 +327,109 @@ fn main() {
         _ => println!("Task B: not found"),
     };
     println!("Total time: {}μs", elapsed);
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::convert::TryInto;
+    use std::convert::{TryFrom, TryInto};
 
     #[test]
     fn test_parse_ok() {
         assert_eq!(Insruction::Noop(1), "nop +1".try_into().unwrap());
         assert_eq!(Insruction::Acc(1), "acc +1".try_into().unwrap());
         assert_eq!(Insruction::Jmp(-100), "jmp -100".try_into().unwrap());
+        assert_eq!(Insruction::Jnz(2, 3), "jnz c, +3".try_into().unwrap());
+        assert_eq!(Insruction::Ret, "ret".try_into().unwrap());
+    }
+
+    #[test]
+    fn test_parse_fail() {
+        assert!(Insruction::try_from("nop").is_err());
+        assert!(Insruction::try_from("jmp a").is_err());
+        assert!(Insruction::try_from("hlt +1").is_err());
+    }
+
+    #[test]
+    fn test_opcode_order() {
+        for op in isa::OPCODES {
+            let ins = op.build(&vec![0; op.operands.len()]);
+            assert_eq!(ins.name(), op.name);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Insruction::Acc(0).to_string(), "acc +0");
+        assert_eq!(Insruction::Mov(3, -1).to_string(), "mov d, -1");
+        assert_eq!(Insruction::Hlt.to_string(), "hlt");
+    }
+
+    #[test]
+    fn test_fault() {
+        let prog = parse("acc +1\njmp -2\nacc +1").unwrap();
+        let mut cpu = CPU::new(&prog);
+
+        assert_eq!(cpu.step(), StepResult::Ok);
+        assert_eq!(cpu.step(), StepResult::Fault(Fault::Jump(-1)));
+        assert_eq!(cpu.step(), StepResult::Fault(Fault::Jump(-1)));
+        assert_eq!(cpu.ip(), 1);
+        assert_eq!(cpu.acc(), 1);
+    }
+
+    #[test]
+    fn test_solver_conditional_loop() {
+        let counted = parse("mov b, +3\nadd b, -1\njnz b, -1\nacc +1\njmp +0").unwrap();
+        let mut cpu = CPU::new(&counted);
+
+        assert!(matches!(
+            Solver::new().run_till_stop(&mut cpu),
+            RunResult::Loop
+        ));
+        assert_eq!(cpu.acc(), 1);
+        assert_eq!(cpu.regs[1], 0);
+
+        let subroutine = parse("call +3\ncall +2\nhlt\nacc +1\nret").unwrap();
+        assert!(matches!(
+            Solver::new().run_till_stop(&mut CPU::new(&subroutine)),
+            RunResult::Stop
+        ));
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let prog = parse("acc +1\ncall +0").unwrap();
+        let mut cpu = CPU::new(&prog);
+
+        for _ in 0..=MAX_STACK {
+            assert_eq!(cpu.step(), StepResult::Ok);
+        }
+        assert_eq!(cpu.step(), StepResult::Fault(Fault::StackOverflow));
+        assert_eq!(cpu.ip(), 1);
+        assert_eq!(cpu.stack.len(), MAX_STACK);
+    }
+
+    #[test]
+    fn test_wrapping_arithmetic() {
+        let prog = parse("mov a, +2147483647\nacc +1\nadd b, -2147483648\nadd b, -1").unwrap();
+        let mut cpu = CPU::new(&prog);
+
+        while cpu.step() == StepResult::Ok {}
+        assert_eq!(cpu.acc(), i32::MIN);
+        assert_eq!(cpu.regs[1], i32::MAX);
+    }
+
+    #[test]
+    fn test_terminate_at_end() {
+        let prog = parse("jmp +2\nacc +1\nacc +2").unwrap();
+        let mut cpu = CPU::new(&prog);
+
+        assert_eq!(cpu.step(), StepResult::Ok);
+        assert_eq!(cpu.step(), StepResult::Ok);
+        assert_eq!(cpu.step(), StepResult::Stop);
+        assert_eq!(cpu.ip(), 3);
+        assert_eq!(cpu.acc(), 2);
     }
 }
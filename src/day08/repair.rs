@@ -0,0 +1,242 @@
+use crate::cfg::{self, Cfg, Target};
+use crate::isa::Insruction;
+use crate::{StepResult, CPU};
+use std::collections::{HashSet, VecDeque};
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Debug, PartialEq)]
+pub struct Repair {
+    /// Indices of the swapped `nop`/`jmp` instructions, in execution order.
+    pub flips: Vec<usize>,
+    /// Accumulator once the repaired program terminates.
+    pub acc: i32,
+}
+
+/// Repair needs the whole control flow to be known up front, which rules out
+/// conditional jumps and calls.
+fn check(prog: &[Insruction]) -> Result<(), Error> {
+    match prog.iter().position(|ins| !cfg::is_static(ins)) {
+        Some(index) => Err(format!(
+            "can't repair programs with `{}` (at {})",
+            prog[index].name(),
+            index
+        )
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Finds the single `nop`/`jmp` swap that makes the program terminate.
+///
+/// Instructions that already reach the end are computed once on the reversed
+/// graph, then the original path is walked for instructions that, flipped,
+/// jump into that set. Candidates are tried in path order until one runs to
+/// the end.
+pub fn repair(prog: &[Insruction]) -> Result<Option<Repair>, Error> {
+    check(prog)?;
+
+    let cfg = Cfg::new(prog);
+    let good = cfg.reaches_end();
+    let mut visited = vec![false; prog.len()];
+    let mut candidates = vec![];
+    let mut ip = 0;
+
+    while ip < prog.len() && !visited[ip] {
+        visited[ip] = true;
+
+        if let Some(flipped) = prog[ip].flipped() {
+            let lands = match cfg::successors(prog.len(), ip, &flipped)[0] {
+                Target::Ins(next) => good[next],
+                Target::End => true,
+                Target::Outside(_) => false,
+            };
+
+            if lands {
+                candidates.push(ip);
+            }
+        }
+
+        match cfg.successors(ip)[0] {
+            Target::Ins(next) => ip = next,
+            _ => break,
+        }
+    }
+
+    Ok(first_fix(prog, candidates))
+}
+
+/// The first single flip among `candidates` that verifies; a candidate
+/// that looked right on the graph but doesn't terminate is skipped.
+fn first_fix(prog: &[Insruction], candidates: Vec<usize>) -> Option<Repair> {
+    candidates
+        .into_iter()
+        .find_map(|index| verify(prog, vec![index]))
+}
+
+/// Finds the fewest `nop`/`jmp` swaps, at most `k`, that make the program
+/// terminate.
+///
+/// A 0-1 BFS where following an instruction costs nothing and following its
+/// flipped version costs one. Shortest paths never revisit an instruction, so
+/// every instruction on the path is either flipped or not, consistently.
+pub fn repair_within(prog: &[Insruction], k: usize) -> Result<Option<Repair>, Error> {
+    check(prog)?;
+
+    let len = prog.len();
+    let mut dist = vec![usize::MAX; len + 1];
+    let mut parent: Vec<Option<(usize, bool)>> = vec![None; len + 1];
+    let mut queue = VecDeque::new();
+
+    dist[0] = 0;
+    queue.push_back(0);
+
+    while let Some(node) = queue.pop_front() {
+        if node == len {
+            break;
+        }
+
+        let mut edges = vec![(&prog[node], false)];
+        let flipped = prog[node].flipped();
+        edges.extend(flipped.as_ref().map(|ins| (ins, true)));
+
+        for (ins, flip) in edges {
+            let cost = dist[node] + flip as usize;
+            let next = match cfg::successors(len, node, ins)[0].node(len) {
+                Some(next) if cost < dist[next] && cost <= k => next,
+                _ => continue,
+            };
+
+            dist[next] = cost;
+            parent[next] = Some((node, flip));
+            if flip {
+                queue.push_back(next);
+            } else {
+                queue.push_front(next);
+            }
+        }
+    }
+
+    if dist[len] > k {
+        return Ok(None);
+    }
+
+    let mut flips = vec![];
+    let mut node = len;
+    while let Some((prev, flip)) = parent[node] {
+        if flip {
+            flips.push(prev);
+        }
+        node = prev;
+    }
+    flips.reverse();
+
+    Ok(verify(prog, flips))
+}
+
+/// Applies the flips and runs the result, to be sure it terminates.
+fn verify(prog: &[Insruction], flips: Vec<usize>) -> Option<Repair> {
+    let mut patched = prog.to_vec();
+    for &index in &flips {
+        patched[index] = patched[index].flipped()?;
+    }
+
+    let mut cpu = CPU::new(&patched);
+    let mut visited = HashSet::new();
+    while visited.insert(cpu.ip()) {
+        match cpu.step() {
+            StepResult::Ok => {}
+            StepResult::Stop => {
+                return Some(Repair {
+                    flips,
+                    acc: cpu.acc(),
+                })
+            }
+            StepResult::Fault(_) => return None,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    const EXAMPLE: &str = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    #[test]
+    fn test_repair() {
+        let prog = parse(EXAMPLE).unwrap();
+        let expected = Repair {
+            flips: vec![7],
+            acc: 8,
+        };
+
+        assert_eq!(repair(&prog).unwrap(), Some(expected));
+    }
+
+    #[test]
+    fn test_first_fix_skips_failed_candidate() {
+        let prog = parse(EXAMPLE).unwrap();
+        let expected = Repair {
+            flips: vec![7],
+            acc: 8,
+        };
+
+        // flipping 0 or 4 still loops
+        assert_eq!(verify(&prog, vec![0]), None);
+        assert_eq!(first_fix(&prog, vec![0, 4, 7, 2]), Some(expected));
+        assert_eq!(first_fix(&prog, vec![0, 4]), None);
+    }
+
+    #[test]
+    fn test_repair_within() {
+        let prog = parse(EXAMPLE).unwrap();
+
+        assert_eq!(repair_within(&prog, 0).unwrap(), None);
+        assert_eq!(repair_within(&prog, 1).unwrap(), repair(&prog).unwrap());
+    }
+
+    #[test]
+    fn test_repair_two_flips() {
+        let prog = parse("acc +1\njmp +0\nacc +2\njmp +0").unwrap();
+        let expected = Repair {
+            flips: vec![1, 3],
+            acc: 3,
+        };
+
+        assert_eq!(repair(&prog).unwrap(), None);
+        assert_eq!(repair_within(&prog, 1).unwrap(), None);
+        assert_eq!(repair_within(&prog, 2).unwrap(), Some(expected));
+    }
+
+    #[test]
+    fn test_repair_terminating() {
+        let prog = parse("acc +1\nnop +5\nacc +1").unwrap();
+        let expected = Repair {
+            flips: vec![],
+            acc: 2,
+        };
+
+        assert_eq!(repair_within(&prog, 3).unwrap(), Some(expected));
+    }
+
+    #[test]
+    fn test_repair_unsupported() {
+        let prog = parse("acc +1\njnz a, -1").unwrap();
+
+        assert!(repair(&prog).is_err());
+        assert!(repair_within(&prog, 1).is_err());
+    }
+
+    #[test]
+    fn test_repair_input() {
+        let prog = parse(include_str!("../../data/day08.txt")).unwrap();
+        let single = repair(&prog).unwrap().unwrap();
+
+        assert_eq!(single.acc, 2212);
+        assert_eq!(repair_within(&prog, 1).unwrap(), Some(single));
+    }
+}
@@ -1,90 +1,67 @@
-use std::collections::VecDeque;
+mod range;
+mod stream;
+
+use std::error::Error;
 use std::fs;
-use std::iter::FromIterator;
+use stream::Validator;
 
 const PREAMBULE: usize = 25;
 
-struct FixedVec<T> {
-    size: usize,
-    inner: VecDeque<T>,
+fn solve_a(data: &str, preambule: usize) -> Result<Option<i64>, Box<dyn Error>> {
+    match Validator::new(data.as_bytes(), preambule)?.next() {
+        Some(invalid) => Ok(Some(invalid?.value)),
+        None => Ok(None),
+    }
 }
 
-impl<T> FixedVec<T> {
-    pub fn new(size: usize) -> Self {
-        assert!(size > 0);
-        Self {
-            size,
-            inner: VecDeque::new(),
-        }
-    }
+fn solve_b(input: &[i64], target: i64) -> Option<i64> {
+    let found = range::ranges(input, target);
+    let (min, max) = range::min_max(input, found.get(..1)?)[0];
 
-    pub fn add(&mut self, item: T) {
-        if self.inner.len() >= self.size {
-            let _ = self.inner.pop_front();
+    Some(min + max)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut preambule = PREAMBULE;
+    let mut all = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--all" => all = true,
+            _ => preambule = arg.parse()?,
         }
-        self.inner.push_back(item);
     }
 
-    pub fn pair_iter(&self) -> impl Iterator<Item = (&T, &T)> {
-        self.inner
-            .iter()
-            .enumerate()
-            .flat_map(move |(index, a)| self.inner.iter().skip(index + 1).map(move |b| (a, b)))
-    }
-}
+    let data = fs::read_to_string("data/day09.txt")?;
+    let input = data
+        .lines()
+        .map(|l| l.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if all {
+        let mut first = None;
+        for invalid in Validator::new(data.as_bytes(), preambule)? {
+            let invalid = invalid?;
+            println!("{}: {}", invalid.position, invalid.value);
+            first = first.or(Some(invalid.value));
+        }
 
-fn solve_a(input: &[i64]) -> Option<i64> {
-    let mut queue = FixedVec::new(PREAMBULE);
-    input[0..PREAMBULE].iter().for_each(|&i| queue.add(i));
-
-    input[PREAMBULE..]
-        .iter()
-        .find(|&&i| {
-            let matched = queue.pair_iter().find(|(&a, &b)| a + b == i);
-            if matched.is_none() {
-                true
-            } else {
-                queue.add(i);
-                false
+        if let Some(target) = first {
+            let found = range::ranges(&input, target);
+            let extremes = range::min_max(&input, &found);
+            for (range, (min, max)) in found.iter().zip(extremes) {
+                println!("{:?}: min {}, max {}, sum {}", range, min, max, min + max);
             }
-        })
-        .copied()
-}
-
-fn solve_b(input: &[i64], target: i64) -> Option<i64> {
-    println!("target: {}", target);
-    let (a, b) = (0..input.len()).find_map(|a| {
-        input[a..]
-            .iter()
-            .enumerate()
-            .scan(0, |state, (index, el)| {
-                *state += el;
-                Some((index, *state))
-            })
-            .take_while(|(_, total)| *total <= target)
-            .find(|(_, total)| *total == target)
-            .map(|(index, _)| (a, a + index))
-    })?;
-
-    let mut clone = Vec::from_iter(input[a..=b].iter().copied());
-    clone.sort_unstable();
-
-    Some(clone[0] + clone[clone.len() - 1])
-}
+        }
 
-fn main() {
-    let data = fs::read_to_string("data/day09.txt").unwrap();
-    let input = data
-        .lines()
-        .map(|l| l.parse::<i64>().ok())
-        .collect::<Option<Vec<_>>>()
-        .unwrap();
+        return Ok(());
+    }
 
-    let value_a = solve_a(&input).unwrap();
-    // let value_b = solve_b(&input, value_a).unwrap();
-    let value_b = solve_b(&input, value_a).unwrap();
+    let value_a = solve_a(&data, preambule)?.ok_or("no invalid number")?;
+    let value_b = solve_b(&input, value_a).ok_or("no range adds up to the invalid number")?;
 
     println!("Task A: {}\nTask B: {}", value_a, value_b);
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -92,23 +69,21 @@ mod test {
     use super::*;
 
     #[test]
-    fn test_add() {
-        let mut fixed_vec = FixedVec::new(2);
-        fixed_vec.add(5);
-        fixed_vec.add(10);
-        fixed_vec.add(15);
+    fn test_solve_a() {
+        let data = "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219";
 
-        assert_eq!(fixed_vec.inner, vec![10, 15]);
+        assert_eq!(solve_a(data, 5).unwrap(), Some(127));
+        assert_eq!(solve_a(data, 2).unwrap(), Some(15));
+        assert!(solve_a(data, 0).is_err());
     }
 
     #[test]
-    fn test_iter() {
-        let mut fixed_vec = FixedVec::new(3);
-        fixed_vec.add(5);
-        fixed_vec.add(10);
-        fixed_vec.add(15);
-
-        let pairs: Vec<_> = fixed_vec.pair_iter().collect();
-        assert_eq!(pairs, vec![(&5, &10), (&5, &15), (&10, &15)]);
+    fn test_solve_b() {
+        let input = [
+            35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127,
+        ];
+
+        assert_eq!(solve_b(&input, 127), Some(62));
+        assert_eq!(solve_b(&input, 1), None);
     }
 }
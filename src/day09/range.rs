@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// Every run of at least two contiguous numbers summing to `target`, ordered
+/// by start, then end.
+///
+/// A run `start..=end` sums to `target` exactly when
+/// `prefix[end + 1] - prefix[start] == target`, so prefix sums seen so far are
+/// kept in a map and looked up for each end. Unlike a two-pointer window this
+/// stays correct with negative numbers.
+pub fn ranges(input: &[i64], target: i64) -> Vec<RangeInclusive<usize>> {
+    let mut prefix = Vec::with_capacity(input.len() + 1);
+    prefix.push(0);
+    for value in input {
+        prefix.push(prefix[prefix.len() - 1] + value);
+    }
+
+    let mut starts: HashMap<i64, Vec<usize>> = HashMap::new();
+    let mut found = vec![];
+
+    for end in 1..input.len() {
+        starts.entry(prefix[end - 1]).or_default().push(end - 1);
+
+        if let Some(matched) = starts.get(&(prefix[end + 1] - target)) {
+            found.extend(matched.iter().map(|&start| start..=end));
+        }
+    }
+
+    found.sort_unstable_by_key(|range| (*range.start(), *range.end()));
+    found
+}
+
+/// Smallest and largest number of each range.
+///
+/// Ranges are answered in order of their end while sweeping the input once,
+/// keeping a stack of indices whose values only increase (for the minimum)
+/// or only decrease (for the maximum). The extreme of `start..=end` is the
+/// first stack entry at or after `start`, found by binary search.
+pub fn min_max(input: &[i64], ranges: &[RangeInclusive<usize>]) -> Vec<(i64, i64)> {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_unstable_by_key(|&i| *ranges[i].end());
+
+    let mut result = vec![(0, 0); ranges.len()];
+    let mut lows: Vec<usize> = vec![];
+    let mut highs: Vec<usize> = vec![];
+    let mut queries = order.into_iter().peekable();
+
+    for (index, &value) in input.iter().enumerate() {
+        while lows.last().is_some_and(|&i| input[i] >= value) {
+            lows.pop();
+        }
+        lows.push(index);
+
+        while highs.last().is_some_and(|&i| input[i] <= value) {
+            highs.pop();
+        }
+        highs.push(index);
+
+        while let Some(query) = queries.next_if(|&q| *ranges[q].end() == index) {
+            let start = *ranges[query].start();
+            let low = lows[lows.partition_point(|&i| i < start)];
+            let high = highs[highs.partition_point(|&i| i < start)];
+            result[query] = (input[low], input[high]);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: [i64; 20] = [
+        35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309, 576,
+    ];
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(ranges(&EXAMPLE, 127), vec![2..=5]);
+        assert_eq!(min_max(&EXAMPLE, &[2..=5]), vec![(15, 47)]);
+    }
+
+    #[test]
+    fn test_ranges_negative() {
+        let input = [3, -2, 4, 1, -1, 2, 5];
+
+        assert_eq!(ranges(&input, 5), vec![0..=2, 0..=4, 2..=3]);
+        assert_eq!(ranges(&input, 3), vec![1..=3]);
+        assert_eq!(ranges(&input, -1), vec![]);
+    }
+
+    #[test]
+    fn test_min_max() {
+        let input = [3, -2, 4, 1, -1, 2, 5];
+        let found = min_max(&input, &[4..=6, 0..=2, 2..=3, 0..=6, 6..=6]);
+
+        assert_eq!(found, vec![(-1, 5), (-2, 4), (1, 4), (-2, 5), (5, 5)]);
+    }
+}
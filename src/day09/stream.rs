@@ -0,0 +1,166 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Lines};
+
+type Error = Box<dyn std::error::Error>;
+
+/// The last `size` numbers, with a multiset of them for pair-sum lookups.
+pub struct Window {
+    size: usize,
+    recent: VecDeque<i64>,
+    counts: HashMap<i64, usize>,
+}
+
+impl Window {
+    pub fn new(size: usize) -> Result<Self, Error> {
+        if size == 0 {
+            return Err("Window size must be at least 1".into());
+        }
+
+        Ok(Self {
+            size,
+            recent: VecDeque::with_capacity(size + 1),
+            counts: HashMap::with_capacity(size),
+        })
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.recent.len() == self.size
+    }
+
+    pub fn push(&mut self, value: i64) {
+        self.recent.push_back(value);
+        *self.counts.entry(value).or_default() += 1;
+
+        if self.recent.len() > self.size {
+            let old = self.recent.pop_front().unwrap();
+            match self.counts.get_mut(&old) {
+                Some(count) if *count > 1 => *count -= 1,
+                _ => {
+                    self.counts.remove(&old);
+                }
+            }
+        }
+    }
+
+    /// Whether two numbers at different positions in the window add up to
+    /// `value`.
+    pub fn is_sum(&self, value: i64) -> bool {
+        self.recent.iter().any(|&a| {
+            let needed = if value - a == a { 2 } else { 1 };
+            self.counts.get(&(value - a)).copied().unwrap_or(0) >= needed
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Invalid {
+    /// Zero-based index of the number in the input.
+    pub position: usize,
+    pub value: i64,
+}
+
+/// Reads numbers one line at a time and yields every one that isn't the sum
+/// of two of the `size` numbers before it.
+pub struct Validator<R> {
+    lines: Lines<R>,
+    window: Window,
+    position: usize,
+}
+
+impl<R: BufRead> Validator<R> {
+    pub fn new(reader: R, size: usize) -> Result<Self, Error> {
+        Ok(Self {
+            lines: reader.lines(),
+            window: Window::new(size)?,
+            position: 0,
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for Validator<R> {
+    type Item = Result<Invalid, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let position = self.position;
+            self.position += 1;
+
+            let value = match line.trim().parse::<i64>() {
+                Ok(value) => value,
+                Err(e) => return Some(Err(format!("line {}: {}", position + 1, e).into())),
+            };
+
+            let valid = !self.window.is_full() || self.window.is_sum(value);
+            self.window.push(value);
+
+            if !valid {
+                return Some(Ok(Invalid { position, value }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str =
+        "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576";
+
+    #[test]
+    fn test_window() {
+        let mut window = Window::new(3).unwrap();
+        [5, 10, 5].iter().for_each(|&v| window.push(v));
+
+        assert!(window.is_full());
+        assert!(window.is_sum(10));
+        assert!(window.is_sum(15));
+        assert!(!window.is_sum(20));
+
+        window.push(15);
+        assert!(!window.is_sum(10));
+        assert!(window.is_sum(25));
+
+        assert!(Window::new(0).is_err());
+    }
+
+    #[test]
+    fn test_validator() {
+        let invalid: Vec<_> = Validator::new(EXAMPLE.as_bytes(), 5)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            invalid,
+            vec![Invalid {
+                position: 14,
+                value: 127
+            }]
+        );
+    }
+
+    #[test]
+    fn test_validator_all() {
+        let invalid: Vec<_> = Validator::new("1\n2\n3\n9\n4\n13\n5".as_bytes(), 2)
+            .unwrap()
+            .map(|r| r.unwrap().position)
+            .collect();
+
+        assert_eq!(invalid, vec![3, 4, 6]);
+    }
+
+    #[test]
+    fn test_validator_error() {
+        let mut validator = Validator::new("1\n2\nthree".as_bytes(), 2).unwrap();
+
+        assert_eq!(
+            validator.next().unwrap().unwrap_err().to_string(),
+            "line 3: invalid digit found in string"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+
+/// Adapters sorted by joltage between the outlet (0) and the device.
+pub struct Chain {
+    /// Outlet, sorted adapters, device.
+    joltages: Vec<u64>,
+    max_gap: u64,
+    /// Arrangements from each joltage on to the device.
+    ways: Vec<BigUint>,
+}
+
+impl Chain {
+    /// The device is rated `offset` above the highest adapter, and every
+    /// step may raise the joltage by at most `max_gap`.
+    pub fn new(adapters: &[u64], max_gap: u64, offset: u64) -> Self {
+        let mut joltages = vec![0];
+        joltages.extend_from_slice(adapters);
+        joltages[1..].sort_unstable();
+        joltages.push(joltages[joltages.len() - 1] + offset);
+
+        let ways = Self::count_ways(&joltages, max_gap);
+
+        Self {
+            joltages,
+            max_gap,
+            ways,
+        }
+    }
+
+    // iterative DP from the device back to the outlet, with a running sum
+    // over the window of joltages reachable in one step
+    fn count_ways(joltages: &[u64], max_gap: u64) -> Vec<BigUint> {
+        let last = joltages.len() - 1;
+        let mut ways = vec![BigUint::zero(); joltages.len()];
+        ways[last] = BigUint::one();
+
+        let mut window = BigUint::one();
+        let mut end = last;
+        for index in (0..last).rev() {
+            while joltages[end] - joltages[index] > max_gap {
+                window -= &ways[end];
+                end -= 1;
+            }
+            ways[index] = window.clone();
+            window += &ways[index];
+        }
+
+        ways
+    }
+
+    /// Outlet, sorted adapters and device.
+    pub fn joltages(&self) -> &[u64] {
+        &self.joltages
+    }
+
+    pub fn max_gap(&self) -> u64 {
+        self.max_gap
+    }
+
+    /// Number of distinct adapter arrangements connecting outlet and device.
+    pub fn count(&self) -> &BigUint {
+        &self.ways[0]
+    }
+
+    // positions one step ahead of `index` that can still reach the device
+    fn next(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let from = self.joltages[index];
+        (index + 1..self.joltages.len())
+            .take_while(move |&j| self.joltages[j] - from <= self.max_gap)
+            .filter(move |&j| !self.ways[j].is_zero())
+    }
+
+    /// Lazily walks all valid arrangements in lexicographic order of the
+    /// adapters used. Dead ends are never entered, so every step of the walk
+    /// leads to an arrangement.
+    pub fn arrangements(&self) -> Arrangements<'_> {
+        let stack = if self.count().is_zero() {
+            vec![]
+        } else {
+            vec![0]
+        };
+
+        Arrangements {
+            chain: self,
+            stack,
+            started: false,
+        }
+    }
+
+    /// Picks one arrangement uniformly at random, stepping to each next
+    /// adapter with probability proportional to the arrangements behind it.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Option<Vec<u64>> {
+        if self.count().is_zero() {
+            return None;
+        }
+
+        let last = self.joltages.len() - 1;
+        let mut used = vec![];
+        let mut index = 0;
+
+        while index != last {
+            let mut pick = rng.gen_biguint_below(&self.ways[index]);
+            index = self
+                .next(index)
+                .find(|&j| {
+                    if pick < self.ways[j] {
+                        true
+                    } else {
+                        pick -= &self.ways[j];
+                        false
+                    }
+                })
+                .unwrap();
+
+            if index != last {
+                used.push(self.joltages[index]);
+            }
+        }
+
+        Some(used)
+    }
+}
+
+pub struct Arrangements<'a> {
+    chain: &'a Chain,
+    /// Positions in the chain of the current arrangement, outlet first.
+    stack: Vec<usize>,
+    started: bool,
+}
+
+impl<'a> Arrangements<'a> {
+    // moves the top of the stack to the next sibling, or pops it
+    fn advance(&mut self) {
+        while let Some(top) = self.stack.pop() {
+            let sibling = self
+                .stack
+                .last()
+                .and_then(|&parent| self.chain.next(parent).find(|&j| j > top));
+
+            if let Some(sibling) = sibling {
+                self.stack.push(sibling);
+                return;
+            }
+        }
+    }
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = self.chain.joltages.len() - 1;
+
+        if self.started {
+            self.advance();
+        }
+        self.started = true;
+
+        let top = *self.stack.last()?;
+        if top != last {
+            let mut index = top;
+            while index != last {
+                index = self.chain.next(index).next().unwrap();
+                self.stack.push(index);
+            }
+        }
+
+        let used = &self.stack[1..self.stack.len() - 1];
+        Some(used.iter().map(|&i| self.chain.joltages[i]).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const SMALL: [u64; 11] = [16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
+
+    #[test]
+    fn test_count() {
+        assert_eq!(Chain::new(&SMALL, 3, 3).count(), &BigUint::from(8u32));
+        assert_eq!(Chain::new(&SMALL, 3, 4).count(), &BigUint::zero());
+        assert_eq!(Chain::new(&SMALL, 2, 3).count(), &BigUint::zero());
+    }
+
+    #[test]
+    fn test_count_large() {
+        let adapters: Vec<u64> = (1..=200).collect();
+        let count = Chain::new(&adapters, 3, 3).count().to_string();
+
+        // tribonacci: far past what fits in 64 bits
+        assert_eq!(
+            count,
+            "52622583840983769603765180599790256716084480555530641"
+        );
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let chain = Chain::new(&SMALL, 3, 3);
+        let all: Vec<_> = chain.arrangements().collect();
+
+        assert_eq!(all.len(), 8);
+        assert_eq!(all[0], vec![1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19]);
+        assert_eq!(all[7], vec![1, 4, 7, 10, 12, 15, 16, 19]);
+
+        let mut sorted = all.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, all);
+    }
+
+    #[test]
+    fn test_arrangements_none() {
+        assert_eq!(Chain::new(&[1, 5], 3, 3).arrangements().count(), 0);
+        assert_eq!(
+            Chain::new(&[2], 3, 3).arrangements().collect::<Vec<_>>(),
+            vec![vec![2]]
+        );
+    }
+
+    #[test]
+    fn test_sample() {
+        let chain = Chain::new(&SMALL, 3, 3);
+        let all: Vec<_> = chain.arrangements().collect();
+        let mut rng = StdRng::seed_from_u64(10);
+
+        for _ in 0..20 {
+            let picked = chain.sample(&mut rng).unwrap();
+            assert!(all.contains(&picked));
+        }
+
+        assert_eq!(Chain::new(&[1, 5], 3, 3).sample(&mut rng), None);
+    }
+}
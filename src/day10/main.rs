@@ -1,97 +1,130 @@
+mod chain;
+mod report;
+
 use advent2020::measure;
-use std::collections::HashMap;
+use chain::Chain;
+use num_bigint::BigUint;
+use report::Report;
 use std::error::Error;
-use std::fs;
+use std::{env, fs};
+
+const MAX_GAP: u64 = 3;
+const OFFSET: u64 = 3;
+
+struct Options {
+    max_gap: u64,
+    offset: u64,
+    list: usize,
+    sample: usize,
+    report: bool,
+}
+
+impl Options {
+    fn parse() -> Result<Self, Box<dyn Error>> {
+        let mut options = Options {
+            max_gap: MAX_GAP,
+            offset: OFFSET,
+            list: 0,
+            sample: 0,
+            report: false,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--report" {
+                options.report = true;
+                continue;
+            }
+
+            let value = args.next().ok_or(format!("{} expects a number", arg))?;
+            match arg.as_str() {
+                "--gap" => options.max_gap = value.parse()?,
+                "--offset" => options.offset = value.parse()?,
+                "--list" => options.list = value.parse()?,
+                "--sample" => options.sample = value.parse()?,
+                _ => return Err(format!("Unknown option: {}", arg).into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
 
-fn read_input() -> Result<Vec<isize>, Box<dyn Error>> {
+fn read_input() -> Result<Vec<u64>, Box<dyn Error>> {
     let raw = fs::read_to_string("data/day10.txt")?;
     let mut result = raw
         .lines()
-        .map(|line| line.parse::<isize>())
+        .map(|line| line.parse::<u64>())
         .collect::<Result<Vec<_>, _>>()?;
     result.sort_unstable();
     Ok(result)
 }
 
-fn solve_a(input: &[isize]) -> Result<isize, Box<dyn Error>> {
-    let max = *input.last().ok_or("Last not found")?;
-    let last = std::iter::once(max + 3);
-    let seq = input.iter().copied().chain(last);
-
-    let mut summary = HashMap::new();
-
-    seq.scan(0, |state, b| {
-        let next = b - *state;
-        *state = b;
-        Some(next)
-    })
-    .for_each(|v| {
-        let entry = summary.entry(v).or_insert(0);
-        *entry += 1;
-    });
+fn solve_a(chain: &Chain) -> Result<usize, Box<dyn Error>> {
+    let report = Report::new(chain);
+    if !report.is_valid() {
+        let gaps = report
+            .invalid
+            .iter()
+            .map(|(from, to)| format!("{} -> {}", from, to))
+            .collect::<Vec<_>>();
+        return Err(format!("Chain impossible, gaps too large: {}", gaps.join(", ")).into());
+    }
 
-    let result = summary.get(&1).copied().unwrap_or(0) * summary.get(&3).copied().unwrap_or(0);
+    Ok(report.count(1) * report.count(3))
+}
 
-    Ok(result)
+fn solve_b(chain: &Chain) -> BigUint {
+    chain.count().clone()
 }
 
-fn solve_b(input: &[isize]) -> Result<isize, Box<dyn Error>> {
-    let max = *input.last().ok_or("Last not found")?;
-    let last = std::iter::once(max + 3);
-    let first = std::iter::once(0);
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse()?;
 
-    let input = first
-        .chain(input.iter().copied())
-        .chain(last)
-        .collect::<Vec<_>>();
+    let input = read_input()?;
+    let (result, elapsed) = measure(|| -> Result<_, Box<dyn Error>> {
+        let chain = Chain::new(&input, options.max_gap, options.offset);
+        let task_a = solve_a(&chain)?;
+        let task_b = solve_b(&chain);
 
-    let mut cache = HashMap::new();
-    let v = permutate_rec(input.as_slice(), &mut cache, 0);
+        Ok((chain, task_a, task_b))
+    });
 
-    Ok(v)
-}
+    let (chain, a, b) = result?;
+    println!("task A: {}\ntask B: {}\nTotal time: {}μs ", a, b, elapsed);
 
-fn permutate_rec(
-    input: &[isize],
-    cache: &mut HashMap<usize, isize>,
-    index_from_start: usize,
-) -> isize {
-    if let Some(i) = cache.get(&index_from_start) {
-        return *i;
+    if options.report {
+        print!("{}", Report::new(&chain));
     }
 
-    if input.len() == 1 {
-        cache.insert(index_from_start, 1);
-        return 1;
+    for arrangement in chain.arrangements().take(options.list) {
+        println!("{:?}", arrangement);
     }
 
-    let start = input[0];
-
-    let result = input
-        .iter()
-        .enumerate()
-        .skip(1)
-        .take_while(|(_, v)| **v - start <= 3)
-        .map(|(index, _)| permutate_rec(&input[index..], cache, index_from_start + index))
-        .sum();
-
-    cache.insert(index_from_start, result);
+    let mut rng = rand::thread_rng();
+    for _ in 0..options.sample {
+        if let Some(arrangement) = chain.sample(&mut rng) {
+            println!("sample: {:?}", arrangement);
+        }
+    }
 
-    result
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let (result, elapsed) = measure(|| -> Result<(isize, isize), Box<dyn Error>> {
-        let input = read_input()?;
-        let task_a = solve_a(&input)?;
-        let task_b = solve_b(&input)?;
-
-        Ok((task_a, task_b))
-    });
-
-    if let Ok((a, b)) = result {
-        println!("task A: {}\ntask B: {}\nTotal time: {}μs ", a, b, elapsed);
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let input = vec![
+            28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25, 35,
+            8, 17, 7, 9, 4, 2, 34, 10, 3,
+        ];
+        let chain = Chain::new(&input, MAX_GAP, OFFSET);
+
+        assert_eq!(solve_a(&chain).unwrap(), 220);
+        assert_eq!(solve_b(&chain), BigUint::from(19208u32));
+        assert!(solve_a(&Chain::new(&input, 2, OFFSET)).is_err());
     }
-
-    Ok(())
 }
@@ -0,0 +1,131 @@
+use crate::chain::Chain;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Joltage differences over the full chain, and what they mean for it.
+pub struct Report {
+    /// How often each difference occurs between neighbouring joltages.
+    pub histogram: BTreeMap<u64, usize>,
+    /// Neighbouring joltages too far apart for any arrangement.
+    pub invalid: Vec<(u64, u64)>,
+    /// Adapters no arrangement can do without; empty for invalid chains.
+    pub mandatory: Vec<u64>,
+    /// Adapters that can be left out; empty for invalid chains.
+    pub removable: Vec<u64>,
+}
+
+impl Report {
+    pub fn new(chain: &Chain) -> Self {
+        let joltages = chain.joltages();
+        let max_gap = chain.max_gap();
+        let mut histogram = BTreeMap::new();
+        let mut invalid = vec![];
+
+        for pair in joltages.windows(2) {
+            let gap = pair[1] - pair[0];
+            *histogram.entry(gap).or_insert(0) += 1;
+            if gap > max_gap {
+                invalid.push((pair[0], pair[1]));
+            }
+        }
+
+        let mut mandatory = vec![];
+        let mut removable = vec![];
+        if invalid.is_empty() {
+            // with the chain valid, an adapter can go exactly when its
+            // neighbours are close enough to connect directly
+            for triple in joltages.windows(3) {
+                if triple[2] - triple[0] > max_gap {
+                    mandatory.push(triple[1]);
+                } else {
+                    removable.push(triple[1]);
+                }
+            }
+        }
+
+        Self {
+            histogram,
+            invalid,
+            mandatory,
+            removable,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.invalid.is_empty()
+    }
+
+    pub fn count(&self, gap: u64) -> usize {
+        self.histogram.get(&gap).copied().unwrap_or(0)
+    }
+}
+
+fn list(values: &[u64]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Differences:")?;
+        for (gap, count) in self.histogram.iter() {
+            writeln!(f, "{:>5} x {}", count, gap)?;
+        }
+
+        if !self.is_valid() {
+            writeln!(f, "Chain impossible, gaps too large:")?;
+            for (from, to) in self.invalid.iter() {
+                writeln!(f, "{:>5} -> {} ({})", from, to, to - from)?;
+            }
+            return Ok(());
+        }
+
+        writeln!(
+            f,
+            "Mandatory ({}): {}",
+            self.mandatory.len(),
+            list(&self.mandatory)
+        )?;
+        writeln!(
+            f,
+            "Removable ({}): {}",
+            self.removable.len(),
+            list(&self.removable)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SMALL: [u64; 11] = [16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4];
+
+    #[test]
+    fn test_report() {
+        let report = Report::new(&Chain::new(&SMALL, 3, 3));
+
+        assert!(report.is_valid());
+        assert_eq!(report.count(1), 7);
+        assert_eq!(report.count(3), 5);
+        assert_eq!(report.mandatory, vec![1, 4, 7, 10, 12, 15, 16, 19]);
+        assert_eq!(report.removable, vec![5, 6, 11]);
+    }
+
+    #[test]
+    fn test_report_invalid() {
+        let report = Report::new(&Chain::new(&[1, 2, 7, 8], 3, 3));
+
+        assert!(!report.is_valid());
+        assert_eq!(report.count(5), 1);
+        assert_eq!(report.invalid, vec![(2, 7)]);
+        assert!(report.mandatory.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "Differences:\n    3 x 1\n    1 x 3\n    1 x 5\nChain impossible, gaps too large:\n    2 -> 7 (5)\n"
+        );
+    }
+}
@@ -1,11 +1,19 @@
 mod direction;
+mod rules;
+mod visibility;
 
 use advent2020::grid::Grid;
-use direction::Direction;
+use advent2020::life::{self, Cell, Rule};
+use advent2020::measure;
+use rules::{Neighbourhood, SeatRules};
 use std::error::Error;
-use std::{fmt, fs};
+use std::{env, fmt, fs};
+use visibility::Layout;
 
-#[derive(PartialEq)]
+/// Rounds to run before deciding a layout never settles.
+const LIMIT: usize = 1000;
+
+#[derive(PartialEq, Debug)]
 enum Seat {
     Floor,
     Empty,
@@ -22,6 +30,20 @@ impl fmt::Display for Seat {
     }
 }
 
+impl Cell for Seat {
+    fn alive(&self) -> bool {
+        *self == Seat::Occupied
+    }
+
+    fn with(&self, alive: bool) -> Self {
+        match self {
+            Seat::Floor => Seat::Floor,
+            _ if alive => Seat::Occupied,
+            _ => Seat::Empty,
+        }
+    }
+}
+
 fn parser(input: char) -> Option<Seat> {
     match input {
         'L' => Some(Seat::Empty),
@@ -30,90 +52,139 @@ fn parser(input: char) -> Option<Seat> {
     }
 }
 
-fn step_task_a(grid: &Grid<Seat>, x: usize, y: usize) -> Seat {
-    let occupied = Direction::all()
-        .filter_map(|d| d.iter(x, y).next())
-        .filter_map(|(x, y)| grid.get(x, y))
-        .filter(|s| **s == Seat::Occupied)
-        .count();
-
-    match grid.get(x as usize, y as usize).unwrap() {
-        Seat::Empty => {
-            if occupied == 0 {
-                Seat::Occupied
-            } else {
-                Seat::Empty
-            }
-        }
-        Seat::Occupied => {
-            if occupied >= 4 {
-                Seat::Empty
-            } else {
-                Seat::Occupied
+fn parse_rules(args: impl Iterator<Item = String>) -> Result<Option<SeatRules>, Box<dyn Error>> {
+    let mut args = args.peekable();
+    let mut rules = None;
+
+    while let Some(arg) = args.next() {
+        let current = rules.get_or_insert(SeatRules::TASK_A);
+        match arg.as_str() {
+            "--adjacent" => current.neighbourhood = Neighbourhood::Adjacent,
+            "--sight" => {
+                current.neighbourhood = Neighbourhood::LineOfSight {
+                    max_distance: args
+                        .next_if(|v| v.parse::<usize>().is_ok())
+                        .map(|v| v.parse())
+                        .transpose()?,
+                }
             }
+            "--tolerance" => current.tolerance = args.next().ok_or("--tolerance N")?.parse()?,
+            "--birth" => current.birth = args.next().ok_or("--birth N")?.parse()?,
+            _ => return Err(format!("Unknown option: {}", arg).into()),
         }
-        Seat::Floor => Seat::Floor,
     }
+
+    Ok(rules)
 }
 
-fn step_task_b(grid: &Grid<Seat>, x: usize, y: usize) -> Seat {
-    let occupied = Direction::all()
-        .filter_map(|d| {
-            d.iter(x, y)
-                .scan((), |(), (x, y)| grid.get(x, y))
-                .fuse()
-                .skip_while(|s| **s == Seat::Floor)
-                .next()
-        })
-        .filter(|s| **s == Seat::Occupied)
-        .count();
-
-    match grid.get(x as usize, y as usize).unwrap() {
-        Seat::Empty => {
-            if occupied == 0 {
-                Seat::Occupied
-            } else {
-                Seat::Empty
-            }
-        }
-        Seat::Occupied => {
-            if occupied >= 5 {
-                Seat::Empty
-            } else {
-                Seat::Occupied
-            }
+fn solve(data: &str, rules: &SeatRules) -> Result<Option<usize>, Box<dyn Error>> {
+    let grid = Grid::parse(data, parser).ok_or("parsing error")?;
+    Layout::new(&grid, rules.neighbourhood).settle(rules, LIMIT)
+}
+
+/// Times ray casting every round against precomputed visibility lists.
+fn bench(data: &str) -> Result<(), Box<dyn Error>> {
+    for (name, rules) in [("A", SeatRules::TASK_A), ("B", SeatRules::TASK_B)] {
+        let mut grid = Grid::parse(data, parser).ok_or("parsing error")?;
+        let (expected, direct) = measure(|| rules.settle(&mut grid, LIMIT));
+
+        let grid = Grid::parse(data, parser).ok_or("parsing error")?;
+        let (layout, build) = measure(|| Layout::new(&grid, rules.neighbourhood));
+        let (occupied, run) = measure(|| layout.settle(&rules, LIMIT));
+        let occupied = occupied?;
+        assert_eq!(occupied, expected);
+
+        println!(
+            "Task {}: ray casting {}μs, visibility lists {}μs (+{}μs to build)",
+            name, direct, run, build
+        );
+    }
+
+    Ok(())
+}
+
+/// Runs a Life-like rule over adjacent seats; seating rules with a plain
+/// tolerance are `B0/S0123` (task A) style rule strings.
+fn solve_life(data: &str, rule: &Rule) -> Result<Option<usize>, Box<dyn Error>> {
+    let mut grid = Grid::parse(data, parser).ok_or("parsing error")?;
+
+    for _ in 0..LIMIT {
+        if life::step(&mut grid, rule, &life::MOORE) == 0 {
+            return Ok(Some(grid.iter().filter(|s| s.alive()).count()));
         }
-        Seat::Floor => Seat::Floor,
     }
+
+    Ok(None)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let data = fs::read_to_string("data/day11.txt")?;
-    let mut grid = Grid::parse(&data, parser).ok_or("parsing error")?;
 
-    loop {
-        let changed = grid.step(step_task_a);
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("--bench") {
+        return bench(&data);
+    }
 
-        if changed == 0 {
-            break;
+    if args.peek().map(String::as_str) == Some("--life") {
+        let rule: Rule = args.nth(1).ok_or("--life expects a rule")?.parse()?;
+        rule.check(life::MOORE.len())?;
+        match solve_life(&data, &rule)? {
+            Some(occupied) => println!("{}: {}", rule, occupied),
+            None => println!("{}: no stable layout in {} rounds", rule, LIMIT),
         }
+        return Ok(());
     }
 
-    let task_a = grid.iter().filter(|item| **item == Seat::Occupied).count();
-    dbg!(task_a);
+    if let Some(rules) = parse_rules(args)? {
+        match solve(&data, &rules)? {
+            Some(occupied) => println!("{:?}: {}", rules, occupied),
+            None => println!("{:?}: no stable layout in {} rounds", rules, LIMIT),
+        }
+        return Ok(());
+    }
 
-    let mut grid = Grid::parse(&data, parser).ok_or("parsing error")?;
+    let task_a = solve(&data, &SeatRules::TASK_A)?.ok_or("task A never settles")?;
+    let task_b = solve(&data, &SeatRules::TASK_B)?.ok_or("task B never settles")?;
+    println!("Task A: {}\nTask B: {}", task_a, task_b);
 
-    loop {
-        let changed = grid.step(step_task_b);
+    Ok(())
+}
 
-        if changed == 0 {
-            break;
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(args: &str) -> SeatRules {
+        parse_rules(args.split_whitespace().map(String::from))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let sight = |max_distance| Neighbourhood::LineOfSight { max_distance };
+
+        assert_eq!(rules("--sight").neighbourhood, sight(None));
+        assert_eq!(rules("--sight 3").neighbourhood, sight(Some(3)));
+        assert_eq!(
+            rules("--sight --tolerance 5"),
+            SeatRules {
+                neighbourhood: sight(None),
+                ..SeatRules::TASK_B
+            }
+        );
+        assert!(parse_rules(vec!["--birth".to_string()].into_iter()).is_err());
     }
 
-    let task_b = grid.iter().filter(|item| **item == Seat::Occupied).count();
-    dbg!(task_b);
+    #[test]
+    fn test_life_rule() {
+        let data = "L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\nL.LLLLL.LL\n..L.L.....\nLLLLLLLLLL\nL.LLLLLL.L\nL.LLLLL.LL";
+        let rule = "B0/S0123".parse().unwrap();
 
-    Ok(())
+        assert_eq!(solve_life(data, &rule).unwrap(), Some(37));
+        assert_eq!(
+            solve_life(data, &rule).unwrap(),
+            solve(data, &SeatRules::TASK_A).unwrap()
+        );
+    }
 }
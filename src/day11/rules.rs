@@ -0,0 +1,144 @@
+use crate::direction::Direction;
+use crate::Seat;
+use advent2020::grid::Grid;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Neighbourhood {
+    /// The eight seats right around.
+    Adjacent,
+    /// The first seat in each of the eight directions, looking over floor,
+    /// at most `max_distance` tiles away when given.
+    LineOfSight { max_distance: Option<usize> },
+}
+
+impl Neighbourhood {
+    /// Positions looked at from `x`, `y`, at most one per direction.
+    pub fn visible<'a>(
+        &self,
+        grid: &'a Grid<Seat>,
+        x: usize,
+        y: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let (skip_floor, max_distance) = match *self {
+            Neighbourhood::Adjacent => (false, Some(1)),
+            Neighbourhood::LineOfSight { max_distance } => (true, max_distance),
+        };
+
+        Direction::all().filter_map(move |d| {
+            d.iter(x, y)
+                .take(max_distance.unwrap_or(usize::MAX))
+                .take_while(|&(x, y)| grid.get(x, y).is_some())
+                .find(|&(x, y)| !skip_floor || grid.get(x, y) != Some(&Seat::Floor))
+        })
+    }
+}
+
+/// How a seating area evolves from one round to the next.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SeatRules {
+    pub neighbourhood: Neighbourhood,
+    /// Occupied seats empty once they see at least this many occupied seats.
+    pub tolerance: usize,
+    /// Empty seats fill once they see at most this many occupied seats.
+    pub birth: usize,
+}
+
+impl SeatRules {
+    pub const TASK_A: SeatRules = SeatRules {
+        neighbourhood: Neighbourhood::Adjacent,
+        tolerance: 4,
+        birth: 0,
+    };
+
+    pub const TASK_B: SeatRules = SeatRules {
+        neighbourhood: Neighbourhood::LineOfSight { max_distance: None },
+        tolerance: 5,
+        birth: 0,
+    };
+
+    fn occupied(&self, grid: &Grid<Seat>, x: usize, y: usize) -> usize {
+        self.neighbourhood
+            .visible(grid, x, y)
+            .filter(|&(x, y)| grid.get(x, y) == Some(&Seat::Occupied))
+            .count()
+    }
+
+    /// The seat at `x`, `y` in the next round.
+    pub fn step(&self, grid: &Grid<Seat>, x: usize, y: usize) -> Seat {
+        match grid.get(x, y).unwrap() {
+            Seat::Floor => Seat::Floor,
+            Seat::Empty if self.occupied(grid, x, y) <= self.birth => Seat::Occupied,
+            Seat::Empty => Seat::Empty,
+            Seat::Occupied if self.occupied(grid, x, y) >= self.tolerance => Seat::Empty,
+            Seat::Occupied => Seat::Occupied,
+        }
+    }
+
+    /// Runs rounds until nothing changes and counts the occupied seats, or
+    /// gives up after `limit` rounds for rules that never settle.
+    pub fn settle(&self, grid: &mut Grid<Seat>, limit: usize) -> Option<usize> {
+        for _ in 0..limit {
+            if grid.step(|grid, x, y| self.step(grid, x, y)) == 0 {
+                return Some(grid.iter().filter(|s| **s == Seat::Occupied).count());
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser;
+
+    const EXAMPLE: &str = "L.LL.LL.LL
+LLLLLLL.LL
+L.L.L..L..
+LLLL.LL.LL
+L.LL.LL.LL
+L.LLLLL.LL
+..L.L.....
+LLLLLLLLLL
+L.LLLLLL.L
+L.LLLLL.LL";
+
+    fn settle(rules: SeatRules) -> Option<usize> {
+        let mut grid = Grid::parse(EXAMPLE, parser).unwrap();
+        rules.settle(&mut grid, 100)
+    }
+
+    #[test]
+    fn test_tasks() {
+        assert_eq!(settle(SeatRules::TASK_A), Some(37));
+        assert_eq!(settle(SeatRules::TASK_B), Some(26));
+    }
+
+    #[test]
+    fn test_line_of_sight_distance() {
+        let sight = |max_distance| SeatRules {
+            neighbourhood: Neighbourhood::LineOfSight { max_distance },
+            ..SeatRules::TASK_A
+        };
+
+        assert_eq!(settle(sight(Some(1))), settle(SeatRules::TASK_A));
+        assert_eq!(
+            settle(sight(Some(100))),
+            settle(SeatRules {
+                tolerance: 4,
+                ..SeatRules::TASK_B
+            })
+        );
+    }
+
+    #[test]
+    fn test_never_settles() {
+        let rules = SeatRules {
+            birth: 8,
+            tolerance: 0,
+            ..SeatRules::TASK_A
+        };
+
+        assert_eq!(settle(rules), None);
+    }
+}
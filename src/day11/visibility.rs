@@ -0,0 +1,159 @@
+use crate::rules::{Neighbourhood, SeatRules};
+use crate::Seat;
+use advent2020::grid::Grid;
+use std::error::Error;
+
+/// Seats of a layout with the seats each of them looks at, computed once so
+/// rounds only walk index lists instead of casting rays through floor.
+pub struct Layout {
+    neighbourhood: Neighbourhood,
+    /// Initial occupancy, one entry per seat in row order.
+    initial: Vec<bool>,
+    /// `visible[start[i]..start[i + 1]]` are the seats seat `i` looks at.
+    start: Vec<usize>,
+    visible: Vec<usize>,
+}
+
+impl Layout {
+    pub fn new(grid: &Grid<Seat>, neighbourhood: Neighbourhood) -> Self {
+        let mut index = vec![None; grid.width * grid.height];
+        let mut seats = vec![];
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                if grid.get(x, y) != Some(&Seat::Floor) {
+                    index[x + y * grid.width] = Some(seats.len());
+                    seats.push((x, y));
+                }
+            }
+        }
+
+        let mut start = vec![0];
+        let mut visible = vec![];
+        for &(x, y) in seats.iter() {
+            visible.extend(
+                neighbourhood
+                    .visible(grid, x, y)
+                    .filter_map(|(x, y)| index[x + y * grid.width]),
+            );
+            start.push(visible.len());
+        }
+
+        let initial = seats
+            .iter()
+            .map(|&(x, y)| grid.get(x, y) == Some(&Seat::Occupied))
+            .collect();
+
+        Self {
+            neighbourhood,
+            initial,
+            start,
+            visible,
+        }
+    }
+
+    /// Same as `SeatRules::settle`, using the precomputed neighbours. The
+    /// rules must look at the neighbourhood the layout was built for.
+    pub fn settle(&self, rules: &SeatRules, limit: usize) -> Result<Option<usize>, Box<dyn Error>> {
+        if rules.neighbourhood != self.neighbourhood {
+            return Err(format!(
+                "Layout built for {:?}, rules use {:?}",
+                self.neighbourhood, rules.neighbourhood
+            )
+            .into());
+        }
+
+        let mut occupied = self.initial.clone();
+        let mut next = occupied.clone();
+
+        for _ in 0..limit {
+            let mut changed = false;
+
+            for (seat, state) in next.iter_mut().enumerate() {
+                let seen = self.visible[self.start[seat]..self.start[seat + 1]]
+                    .iter()
+                    .filter(|&&other| occupied[other])
+                    .count();
+
+                *state = if occupied[seat] {
+                    seen < rules.tolerance
+                } else {
+                    seen <= rules.birth
+                };
+                changed |= *state != occupied[seat];
+            }
+
+            std::mem::swap(&mut occupied, &mut next);
+            if !changed {
+                return Ok(Some(occupied.iter().filter(|&&s| s).count()));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser;
+
+    const EXAMPLE: &str = "L.LL.LL.LL
+LLLLLLL.LL
+L.L.L..L..
+LLLL.LL.LL
+L.LL.LL.LL
+L.LLLLL.LL
+..L.L.....
+LLLLLLLLLL
+L.LLLLLL.L
+L.LLLLL.LL";
+
+    fn compare(rules: SeatRules) {
+        let mut grid = Grid::parse(EXAMPLE, parser).unwrap();
+        let layout = Layout::new(&grid, rules.neighbourhood);
+
+        assert_eq!(
+            layout.settle(&rules, 100).unwrap(),
+            rules.settle(&mut grid, 100)
+        );
+    }
+
+    #[test]
+    fn test_same_as_rules() {
+        compare(SeatRules::TASK_A);
+        compare(SeatRules::TASK_B);
+        compare(SeatRules {
+            neighbourhood: Neighbourhood::LineOfSight {
+                max_distance: Some(2),
+            },
+            birth: 1,
+            ..SeatRules::TASK_B
+        });
+    }
+
+    #[test]
+    fn test_counts() {
+        let grid = Grid::parse(EXAMPLE, parser).unwrap();
+
+        assert_eq!(
+            Layout::new(&grid, Neighbourhood::Adjacent)
+                .settle(&SeatRules::TASK_A, 100)
+                .unwrap(),
+            Some(37)
+        );
+        assert_eq!(
+            Layout::new(&grid, SeatRules::TASK_B.neighbourhood)
+                .settle(&SeatRules::TASK_B, 100)
+                .unwrap(),
+            Some(26)
+        );
+    }
+
+    #[test]
+    fn test_mismatched_neighbourhood() {
+        let grid = Grid::parse(EXAMPLE, parser).unwrap();
+        let layout = Layout::new(&grid, Neighbourhood::Adjacent);
+
+        assert!(layout.settle(&SeatRules::TASK_B, 100).is_err());
+    }
+}
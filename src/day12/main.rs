@@ -1,6 +1,11 @@
+mod route;
+mod trig;
+
+use route::Track;
 use std::convert::TryFrom;
 use std::error::Error;
-use std::fs;
+use std::{env, fs};
+use trig::TrigWorld;
 
 #[derive(Debug)]
 struct Point {
@@ -34,6 +39,10 @@ impl Point {
         }
     }
 
+    fn position(&self) -> (f64, f64) {
+        (self.x as f64, self.y as f64)
+    }
+
     fn turn(&mut self) {
         let x = -self.y;
         let y = self.x;
@@ -52,6 +61,21 @@ enum Direction {
 }
 
 impl Direction {
+    /// Compass heading: 0 is north, 90 east.
+    fn from_degrees(degrees: isize) -> Result<Self, Box<dyn Error>> {
+        match degrees.rem_euclid(360) {
+            0 => Ok(Self::North),
+            90 => Ok(Self::East),
+            180 => Ok(Self::South),
+            270 => Ok(Self::West),
+            _ => Err(format!(
+                "Heading {}° is not a compass direction; use --trig",
+                degrees
+            )
+            .into()),
+        }
+    }
+
     fn turn(self) -> Self {
         match self {
             Self::North => Self::East,
@@ -68,6 +92,10 @@ enum Action {
     Left(isize),
     Right(isize),
     Forward(isize),
+    /// `H<degrees>`: face a compass heading.
+    Heading(isize),
+    /// `T<degrees>,<amount>`: move toward a compass heading, like `N`/`E`/`S`/`W`.
+    Toward(isize, isize),
 }
 
 impl TryFrom<&str> for Action {
@@ -75,6 +103,13 @@ impl TryFrom<&str> for Action {
 
     fn try_from(input: &str) -> Result<Self, Self::Error> {
         let action = input.chars().next().ok_or("Empty input")?;
+        if action == 'T' {
+            let (degrees, amount) = input[1..]
+                .split_once(',')
+                .ok_or_else(|| format!("Invalid input: {}", input))?;
+            return Ok(Action::Toward(degrees.parse()?, amount.parse()?));
+        }
+
         let value = input[1..].parse::<isize>()?;
 
         match action {
@@ -85,6 +120,7 @@ impl TryFrom<&str> for Action {
             'L' => Ok(Action::Left(value)),
             'R' => Ok(Action::Right(value)),
             'F' => Ok(Action::Forward(value)),
+            'H' => Ok(Action::Heading(value)),
             _ => Err(format!("Invalid input: {}", input).into()),
         }
     }
@@ -95,14 +131,20 @@ struct World {
     ship: Point,
     waypoint: Point,
     dir: Direction,
+    track: Track,
 }
 
 impl World {
     fn new() -> Self {
+        let ship = Point::new(0, 0);
+        let waypoint = Point::new(10, -1);
+        let track = Track::new(ship.position(), waypoint.position());
+
         Self {
-            ship: Point::new(0, 0),
-            waypoint: Point::new(10, -1),
+            ship,
+            waypoint,
             dir: Direction::East,
+            track,
         }
     }
 
@@ -110,62 +152,200 @@ impl World {
         self.ship.x.abs() + self.ship.y.abs()
     }
 
-    fn step(&mut self, action: &Action) {
+    fn step(&mut self, action: &Action) -> Result<(), Box<dyn Error>> {
         match action {
             Action::Move(dir, amount) => self.ship.step(dir, *amount),
             Action::Forward(value) => self.ship.step(&self.dir, *value),
-            Action::Left(value) => self.turn(*value * -1),
-            Action::Right(value) => self.turn(*value),
+            Action::Left(value) => self.turn(-*value)?,
+            Action::Right(value) => self.turn(*value)?,
+            Action::Heading(degrees) => self.dir = Direction::from_degrees(*degrees)?,
+            Action::Toward(degrees, amount) => {
+                self.ship.step(&Direction::from_degrees(*degrees)?, *amount)
+            }
         }
+
+        self.track
+            .push(self.ship.position(), self.waypoint.position());
+        Ok(())
     }
 
-    fn step_relative(&mut self, action: &Action) {
+    fn step_relative(&mut self, action: &Action) -> Result<(), Box<dyn Error>> {
         match action {
             Action::Move(dir, amount) => self.waypoint.step(dir, *amount),
             Action::Forward(value) => self.ship.step_by(&self.waypoint.times(*value)),
-            Action::Left(value) => self.turn_waypoint(*value * -1),
-            Action::Right(value) => self.turn_waypoint(*value),
+            Action::Left(value) => self.turn_waypoint(-*value)?,
+            Action::Right(value) => self.turn_waypoint(*value)?,
+            Action::Heading(degrees) => self.dir = Direction::from_degrees(*degrees)?,
+            Action::Toward(degrees, amount) => self
+                .waypoint
+                .step(&Direction::from_degrees(*degrees)?, *amount),
         }
+
+        self.track
+            .push(self.ship.position(), self.waypoint.position());
+        Ok(())
     }
 
-    fn turn(&mut self, angle: isize) {
-        let angle = ((angle / 90) % 4 + 4) % 4;
+    // number of clockwise quarter turns
+    fn quarters(angle: isize) -> Result<isize, Box<dyn Error>> {
+        if angle % 90 != 0 {
+            return Err(format!("Can't turn by {}° in integer mode; use --trig", angle).into());
+        }
 
-        for _ in 0..angle {
+        Ok((angle / 90).rem_euclid(4))
+    }
+
+    fn turn(&mut self, angle: isize) -> Result<(), Box<dyn Error>> {
+        for _ in 0..Self::quarters(angle)? {
             self.dir = self.dir.turn()
         }
-    }
 
-    fn turn_waypoint(&mut self, angle: isize) {
-        let angle = ((angle / 90) % 4 + 4) % 4;
+        Ok(())
+    }
 
-        for _ in 0..angle {
+    fn turn_waypoint(&mut self, angle: isize) -> Result<(), Box<dyn Error>> {
+        for _ in 0..Self::quarters(angle)? {
             self.waypoint.turn()
         }
+
+        Ok(())
+    }
+}
+
+struct Options {
+    trig: bool,
+    route: bool,
+    svg: Option<String>,
+}
+
+impl Options {
+    fn parse() -> Result<Self, Box<dyn Error>> {
+        let mut options = Options {
+            trig: false,
+            route: false,
+            svg: None,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--trig" => options.trig = true,
+                "--route" => options.route = true,
+                "--svg" => options.svg = Some(args.next().ok_or("--svg needs a file")?),
+                _ => return Err(format!("Unknown argument: {}", arg).into()),
+            }
+        }
+
+        Ok(options)
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse()?;
     let data = fs::read_to_string("data/day12.txt")?;
     let input = data
         .lines()
         .map(Action::try_from)
         .collect::<Result<Vec<_>, _>>()?;
 
-    let mut world = World::new();
+    let (a, b) = if options.trig {
+        let mut world = TrigWorld::new();
+        input.iter().for_each(|action| world.step(action));
+        let a = (format!("{:.3}", world.distance_from_start()), world.track);
 
-    for action in input.iter() {
-        world.step(action);
-    }
+        let mut world = TrigWorld::new();
+        input.iter().for_each(|action| world.step_relative(action));
+        let b = (format!("{:.3}", world.distance_from_start()), world.track);
+
+        (a, b)
+    } else {
+        let mut world = World::new();
+        for action in input.iter() {
+            world.step(action)?;
+        }
+        let a = (world.distance_from_start().to_string(), world.track);
+
+        let mut world = World::new();
+        for action in input.iter() {
+            world.step_relative(action)?;
+        }
+        let b = (world.distance_from_start().to_string(), world.track);
 
-    println!("Task A: {}", world.distance_from_start());
+        (a, b)
+    };
 
-    let mut world = World::new();
-    for action in input.iter() {
-        world.step_relative(action);
+    println!("Task A: {}", a.0);
+    println!("Task B: {}", b.0);
+
+    if options.route {
+        println!("\nTask A route\n{}", a.1);
+        println!("\nTask B route\n{}", b.1);
     }
 
-    println!("Task B: {}", world.distance_from_start());
+    if let Some(path) = options.svg {
+        route::save_svg(
+            &[("Task A: heading", &a.1), ("Task B: waypoint", &b.1)],
+            &path,
+        )?;
+        println!("Routes written to {}", path);
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Action> {
+        input
+            .lines()
+            .map(|l| Action::try_from(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_example() {
+        let input = parse("F10\nN3\nF7\nR90\nF11");
+        let mut world = World::new();
+        let mut relative = World::new();
+        for action in input.iter() {
+            world.step(action).unwrap();
+            relative.step_relative(action).unwrap();
+        }
+
+        assert_eq!(world.distance_from_start(), 25);
+        assert_eq!(relative.distance_from_start(), 286);
+
+        assert_eq!(world.track.ship.len(), 6);
+        assert_eq!(world.track.farthest(), (5, (17.0, 8.0)));
+        assert_eq!(relative.track.waypoint[4], (4.0, 10.0));
+        assert_eq!(relative.track.bounds().max, (214.0, 72.0));
+    }
+
+    #[test]
+    fn test_integer_mode_errors() {
+        let mut world = World::new();
+
+        assert!(world.step(&Action::Right(-270)).is_ok());
+        assert!(matches!(world.dir, Direction::South));
+        assert_eq!(
+            world.step(&Action::Left(45)).unwrap_err().to_string(),
+            "Can't turn by -45° in integer mode; use --trig"
+        );
+        assert!(world.step_relative(&Action::Right(30)).is_err());
+        assert!(world.step(&Action::Heading(100)).is_err());
+    }
+
+    #[test]
+    fn test_heading_actions() {
+        let input = parse("H270\nF5\nT180,2\nT-90,1");
+        let mut world = World::new();
+        for action in input.iter() {
+            world.step(action).unwrap();
+        }
+
+        assert_eq!((world.ship.x, world.ship.y), (-6, 2));
+        assert!(Action::try_from("T45").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const COLOURS: [&str; 4] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd"];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Bounds {
+    pub min: (f64, f64),
+    pub max: (f64, f64),
+}
+
+impl Bounds {
+    fn of<'a>(points: impl Iterator<Item = &'a (f64, f64)>) -> Self {
+        points.fold(
+            Bounds {
+                min: (f64::INFINITY, f64::INFINITY),
+                max: (f64::NEG_INFINITY, f64::NEG_INFINITY),
+            },
+            |b, &(x, y)| Bounds {
+                min: (b.min.0.min(x), b.min.1.min(y)),
+                max: (b.max.0.max(x), b.max.1.max(y)),
+            },
+        )
+    }
+
+    fn union(&self, other: &Bounds) -> Self {
+        Bounds::of([self.min, self.max, other.min, other.max].iter())
+    }
+}
+
+/// Ship and waypoint positions at the start and after every action.
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub ship: Vec<(f64, f64)>,
+    pub waypoint: Vec<(f64, f64)>,
+}
+
+impl Track {
+    pub fn new(ship: (f64, f64), waypoint: (f64, f64)) -> Self {
+        Self {
+            ship: vec![ship],
+            waypoint: vec![waypoint],
+        }
+    }
+
+    pub fn push(&mut self, ship: (f64, f64), waypoint: (f64, f64)) {
+        self.ship.push(ship);
+        self.waypoint.push(waypoint);
+    }
+
+    /// Box around every position the ship has been at.
+    pub fn bounds(&self) -> Bounds {
+        Bounds::of(self.ship.iter())
+    }
+
+    /// Length of the ship's path, in straight lines between positions.
+    pub fn distance(&self) -> f64 {
+        self.ship
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+            .sum()
+    }
+
+    /// Number of actions taken and the position reached when the ship was
+    /// farthest from the start, by Manhattan distance. The first is kept on
+    /// ties.
+    pub fn farthest(&self) -> (usize, (f64, f64)) {
+        let start = self.ship[0];
+        let manhattan = |p: &(f64, f64)| (p.0 - start.0).abs() + (p.1 - start.1).abs();
+
+        self.ship
+            .iter()
+            .enumerate()
+            .fold((0, start), |best, (index, point)| {
+                if manhattan(point) > manhattan(&best.1) {
+                    (index, *point)
+                } else {
+                    best
+                }
+            })
+    }
+}
+
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bounds = self.bounds();
+        let (index, (x, y)) = self.farthest();
+
+        writeln!(
+            f,
+            "bounds: ({}, {}) to ({}, {})",
+            bounds.min.0, bounds.min.1, bounds.max.0, bounds.max.1
+        )?;
+        writeln!(f, "travelled: {:.3}", self.distance())?;
+        write!(f, "farthest: ({}, {}) after {} actions", x, y, index)
+    }
+}
+
+/// Draws the ship paths of all `routes` on one canvas, north up, each in its
+/// own colour with a legend, the start and the farthest point marked.
+pub fn write_svg<W: Write>(routes: &[(&str, &Track)], out: &mut W) -> io::Result<()> {
+    let bounds = routes
+        .iter()
+        .map(|(_, track)| track.bounds())
+        .reduce(|a, b| a.union(&b))
+        .unwrap_or(Bounds {
+            min: (0.0, 0.0),
+            max: (0.0, 0.0),
+        });
+
+    let size = (bounds.max.0 - bounds.min.0)
+        .max(bounds.max.1 - bounds.min.1)
+        .max(1.0);
+    let margin = size / 20.0;
+    let (x, y) = (bounds.min.0 - margin, bounds.min.1 - margin);
+    let (width, height) = (
+        bounds.max.0 - bounds.min.0 + 2.0 * margin,
+        bounds.max.1 - bounds.min.1 + 2.0 * margin,
+    );
+    let dot = size / 150.0;
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="800">"#,
+        x, y, width, height
+    )?;
+    writeln!(
+        out,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="white"/>"#,
+        x, y, width, height
+    )?;
+
+    for (index, (name, track)) in routes.iter().enumerate() {
+        let colour = COLOURS[index % COLOURS.len()];
+        let points: Vec<String> = track
+            .ship
+            .iter()
+            .map(|(x, y)| format!("{},{}", x, y))
+            .collect();
+        let (_, far) = track.farthest();
+
+        writeln!(
+            out,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2" vector-effect="non-scaling-stroke"/>"#,
+            points.join(" "),
+            colour
+        )?;
+        writeln!(
+            out,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+            far.0, far.1, dot, colour
+        )?;
+        writeln!(
+            out,
+            r#"<text x="{}" y="{}" font-size="{}" fill="{}">{}</text>"#,
+            x + margin / 4.0,
+            y + margin * (index + 1) as f64 / 2.0,
+            margin / 2.5,
+            colour,
+            name
+        )?;
+    }
+
+    if let Some((_, track)) = routes.first() {
+        let (sx, sy) = track.ship[0];
+        writeln!(
+            out,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="black"/>"#,
+            sx, sy, dot
+        )?;
+    }
+
+    writeln!(out, "</svg>")
+}
+
+pub fn save_svg<P: AsRef<Path>>(routes: &[(&str, &Track)], path: P) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_svg(routes, &mut out)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn track() -> Track {
+        let mut track = Track::new((0.0, 0.0), (10.0, -1.0));
+        track.push((3.0, 0.0), (10.0, -1.0));
+        track.push((3.0, -4.0), (10.0, -1.0));
+        track.push((0.0, 0.0), (1.0, 10.0));
+        track
+    }
+
+    #[test]
+    fn test_stats() {
+        let track = track();
+
+        assert_eq!(
+            track.bounds(),
+            Bounds {
+                min: (0.0, -4.0),
+                max: (3.0, 0.0)
+            }
+        );
+        assert_eq!(track.distance(), 12.0);
+        assert_eq!(track.farthest(), (2, (3.0, -4.0)));
+        assert_eq!(
+            track.to_string(),
+            "bounds: (0, -4) to (3, 0)\ntravelled: 12.000\nfarthest: (3, -4) after 2 actions"
+        );
+    }
+
+    #[test]
+    fn test_svg() {
+        let mut out = vec![];
+        write_svg(&[("A", &track())], &mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"viewBox="-0.2 -4.2 3.4 4.4""#));
+        assert!(svg.contains(r#"points="0,0 3,0 3,-4 0,0""#));
+        assert!(svg.contains(">A</text>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::route::Track;
+use crate::{Action, Direction};
+
+/// Sine and cosine of a clockwise angle in degrees. Multiples of 90 are
+/// exact, so axis-aligned routes stay on whole numbers.
+fn sin_cos(degrees: f64) -> (f64, f64) {
+    let degrees = degrees.rem_euclid(360.0);
+    match degrees {
+        0.0 => (0.0, 1.0),
+        90.0 => (1.0, 0.0),
+        180.0 => (0.0, -1.0),
+        270.0 => (-1.0, 0.0),
+        d => d.to_radians().sin_cos(),
+    }
+}
+
+/// Unit step toward a compass heading, y growing south.
+fn unit(heading: f64) -> (f64, f64) {
+    let (sin, cos) = sin_cos(heading);
+    (sin, -cos)
+}
+
+fn heading_of(dir: &Direction) -> f64 {
+    match dir {
+        Direction::North => 0.0,
+        Direction::East => 90.0,
+        Direction::South => 180.0,
+        Direction::West => 270.0,
+    }
+}
+
+/// The ship with a free heading and waypoint, turning by any angle.
+#[derive(Debug)]
+pub struct TrigWorld {
+    pub ship: (f64, f64),
+    pub waypoint: (f64, f64),
+    /// Compass degrees, 0 is north.
+    pub heading: f64,
+    pub track: Track,
+}
+
+impl TrigWorld {
+    pub fn new() -> Self {
+        Self {
+            ship: (0.0, 0.0),
+            waypoint: (10.0, -1.0),
+            heading: 90.0,
+            track: Track::new((0.0, 0.0), (10.0, -1.0)),
+        }
+    }
+
+    pub fn distance_from_start(&self) -> f64 {
+        self.ship.0.abs() + self.ship.1.abs()
+    }
+
+    fn advance(point: &mut (f64, f64), heading: f64, amount: isize) {
+        let (dx, dy) = unit(heading);
+        point.0 += dx * amount as f64;
+        point.1 += dy * amount as f64;
+    }
+
+    fn rotate(&mut self, angle: isize) {
+        let (sin, cos) = sin_cos(angle as f64);
+        let (x, y) = self.waypoint;
+        self.waypoint = (x * cos - y * sin, x * sin + y * cos);
+    }
+
+    pub fn step(&mut self, action: &Action) {
+        match action {
+            Action::Move(dir, amount) => Self::advance(&mut self.ship, heading_of(dir), *amount),
+            Action::Forward(value) => Self::advance(&mut self.ship, self.heading, *value),
+            Action::Left(value) => self.heading -= *value as f64,
+            Action::Right(value) => self.heading += *value as f64,
+            Action::Heading(degrees) => self.heading = *degrees as f64,
+            Action::Toward(degrees, amount) => {
+                Self::advance(&mut self.ship, *degrees as f64, *amount)
+            }
+        }
+
+        self.track.push(self.ship, self.waypoint);
+    }
+
+    pub fn step_relative(&mut self, action: &Action) {
+        match action {
+            Action::Move(dir, amount) => {
+                Self::advance(&mut self.waypoint, heading_of(dir), *amount)
+            }
+            Action::Forward(value) => {
+                self.ship.0 += self.waypoint.0 * *value as f64;
+                self.ship.1 += self.waypoint.1 * *value as f64;
+            }
+            Action::Left(value) => self.rotate(-*value),
+            Action::Right(value) => self.rotate(*value),
+            Action::Heading(degrees) => self.heading = *degrees as f64,
+            Action::Toward(degrees, amount) => {
+                Self::advance(&mut self.waypoint, *degrees as f64, *amount)
+            }
+        }
+
+        self.track.push(self.ship, self.waypoint);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn run(input: &str, relative: bool) -> TrigWorld {
+        let mut world = TrigWorld::new();
+        for line in input.lines() {
+            let action = Action::try_from(line).unwrap();
+            if relative {
+                world.step_relative(&action);
+            } else {
+                world.step(&action);
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn test_matches_integer_mode() {
+        let input = "F10\nN3\nF7\nR90\nF11\nL270\nF3";
+
+        assert_eq!(run(input, false).distance_from_start(), 25.0 - 3.0);
+        assert_eq!(run(input, true).distance_from_start(), 268.0);
+    }
+
+    #[test]
+    fn test_arbitrary_angles() {
+        let world = run("L45\nF2\nR90\nF2", false);
+        let root2 = 2f64.sqrt();
+        assert!((world.ship.0 - 2.0 * root2).abs() < 1e-9);
+        assert!(world.ship.1.abs() < 1e-9);
+
+        let world = run("R60\nR30\nF1", true);
+        assert!((world.ship.0 - 1.0).abs() < 1e-9);
+        assert!((world.ship.1 - 10.0).abs() < 1e-9);
+
+        let world = run("T30,2", false);
+        assert!((world.ship.0 - 1.0).abs() < 1e-9);
+        assert!((world.ship.1 + 3f64.sqrt()).abs() < 1e-9);
+    }
+}
@@ -1,9 +1,9 @@
 use advent2020::grid::Grid;
+use advent2020::life::{Cell, Pattern, Rule};
 use std::collections::HashSet;
 use std::error::Error;
-use std::fmt;
-use std::fs;
 use std::ops::Sub;
+use std::{env, fmt, fs};
 
 trait Point: Clone + Eq + core::hash::Hash {
     fn from_xy(x: isize, y: isize) -> Self;
@@ -324,20 +324,17 @@ where
         self.world.len()
     }
 
-    pub fn step(&self) -> Self {
+    /// Next generation under a Life-like rule, with `Point::around` as the
+    /// neighbourhood. Only points next to the active region are considered,
+    /// so birth on zero neighbours (`B0`) stays local.
+    pub fn step(&self, rule: &Rule) -> Self {
         let mut space = Space::new();
 
         for point in self.points() {
             let around = point.around().filter(|p| self.active(p)).count();
 
-            if self.active(&point) {
-                if around == 2 || around == 3 {
-                    space.add_point(point)
-                }
-            } else {
-                if around == 3 {
-                    space.add_point(point)
-                }
+            if rule.next(self.active(&point), around) {
+                space.add_point(point)
             }
         }
 
@@ -390,6 +387,29 @@ enum Element {
     Inactive,
 }
 
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Element::Active => write!(f, "#"),
+            Element::Inactive => write!(f, "."),
+        }
+    }
+}
+
+impl Cell for Element {
+    fn alive(&self) -> bool {
+        *self == Element::Active
+    }
+
+    fn with(&self, alive: bool) -> Self {
+        if alive {
+            Element::Active
+        } else {
+            Element::Inactive
+        }
+    }
+}
+
 fn parser(input: char) -> Option<Element> {
     match input {
         '#' => Some(Element::Active),
@@ -398,27 +418,53 @@ fn parser(input: char) -> Option<Element> {
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let raw = fs::read_to_string("data/day17.txt")?;
-    let grid = Grid::parse(&raw, parser).ok_or("can't parse input")?;
-    let mut space: Space<Point3> = Space::from_grid(grid);
+/// Size of the neighbourhood of a point in `T`'s space.
+fn neighbours<T: Point>() -> usize {
+    T::from_xy(0, 0).around().count()
+}
+
+fn run<T>(grid: Grid<Element>, rule: &Rule) -> usize
+where
+    T: Point + DimensionBound + Clone + 'static,
+{
+    let mut space: Space<T> = Space::from_grid(grid);
 
     for _ in 0..6 {
-        // println!("{}", space);
-        space = space.step();
+        space = space.step(rule);
     }
 
-    dbg!(space.total());
+    space.total()
+}
 
-    let grid = Grid::parse(&raw, parser).ok_or("can't parse input")?;
-    let mut space: Space<Point4> = Space::from_grid(grid);
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut raw = fs::read_to_string("data/day17.txt")?;
+    let mut rule = Rule::LIFE;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rule" => rule = args.next().ok_or("--rule expects a rule string")?.parse()?,
+            "--rle" => {
+                let path = args.next().ok_or("--rle expects a pattern file")?;
+                let pattern = Pattern::parse_rle(&fs::read_to_string(path)?)?;
+                rule = pattern.rule.unwrap_or(rule);
+                raw = pattern.to_grid(0, Element::Inactive).to_string();
+            }
+            _ => return Err(format!("Unknown option: {}", arg).into()),
+        }
+    }
 
-    for _ in 0..6 {
-        // println!("{}", space);
-        space = space.step();
+    let grid = Grid::parse(&raw, parser).ok_or("can't parse input")?;
+    match rule.check(neighbours::<Point3>()) {
+        Ok(()) => println!("Task A: {}", run::<Point3>(grid, &rule)),
+        Err(e) => println!("Task A: {}", e),
     }
 
-    dbg!(space.total());
+    let grid = Grid::parse(&raw, parser).ok_or("can't parse input")?;
+    match rule.check(neighbours::<Point4>()) {
+        Ok(()) => println!("Task B: {}", run::<Point4>(grid, &rule)),
+        Err(e) => println!("Task B: {}", e),
+    }
 
     Ok(())
 }
@@ -452,4 +498,30 @@ mod test {
 
         assert_eq!(d.points().count(), 120);
     }
+
+    #[test]
+    fn test_example() {
+        let grid = || Grid::parse(".#.\n..#\n###", parser).unwrap();
+
+        assert_eq!(run::<Point3>(grid(), &Rule::LIFE), 112);
+        assert_eq!(run::<Point4>(grid(), &Rule::LIFE), 848);
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let rule: Rule = "B3/S2,3,30".parse().unwrap();
+
+        assert!(rule.check(neighbours::<Point3>()).is_err());
+        assert!(rule.check(neighbours::<Point4>()).is_ok());
+        assert_eq!(neighbours::<Point4>(), 80);
+    }
+
+    #[test]
+    fn test_rle_glider() {
+        let pattern = Pattern::parse_rle("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+        let grid = pattern.to_grid(0, Element::Inactive);
+
+        assert_eq!(Grid::parse(&grid.to_string(), parser).unwrap().width, 3);
+        assert_eq!(run::<Point3>(grid, &pattern.rule.unwrap()), 112);
+    }
 }
@@ -13,6 +13,24 @@ impl<T> Grid<T>
 where
     T: std::cmp::PartialEq,
 {
+    pub fn from_fn<F>(width: usize, height: usize, builder: F) -> Grid<T>
+    where
+        F: Fn(usize, usize) -> T,
+    {
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                data.push(builder(x, y));
+            }
+        }
+
+        Grid {
+            data,
+            width,
+            height,
+        }
+    }
+
     pub fn step<F>(&mut self, changer: F) -> usize
     where
         F: Fn(&Grid<T>, usize, usize) -> T,
@@ -0,0 +1,49 @@
+use crate::grid::Grid;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub trait Pixel {
+    fn rgb(&self) -> [u8; 3];
+}
+
+/// Writes the grid as a binary PPM image, every cell drawn as a `scale`x`scale` square.
+pub fn write_ppm<T, W>(grid: &Grid<T>, scale: usize, out: &mut W) -> io::Result<()>
+where
+    T: Pixel + PartialEq,
+    W: Write,
+{
+    let scale = scale.max(1);
+    write!(
+        out,
+        "P6\n{} {}\n255\n",
+        grid.width * scale,
+        grid.height * scale
+    )?;
+
+    for y in 0..grid.height {
+        let mut line = Vec::with_capacity(grid.width * scale * 3);
+        for x in 0..grid.width {
+            let rgb = grid.get(x, y).unwrap().rgb();
+            for _ in 0..scale {
+                line.extend_from_slice(&rgb);
+            }
+        }
+
+        for _ in 0..scale {
+            out.write_all(&line)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn save_ppm<T, P>(grid: &Grid<T>, scale: usize, path: P) -> io::Result<()>
+where
+    T: Pixel + PartialEq,
+    P: AsRef<Path>,
+{
+    let mut out = BufWriter::new(File::create(path)?);
+    write_ppm(grid, scale, &mut out)?;
+    out.flush()
+}
@@ -1,4 +1,6 @@
 pub mod grid;
+pub mod image;
+pub mod life;
 use std::time::Instant;
 
 pub fn measure<T, F>(f: F) -> (T, u128)
@@ -0,0 +1,385 @@
+use crate::grid::Grid;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Birth/survival rule of a Life-like automaton, written as a rule string
+/// such as `B3/S23`: dead cells with 3 live neighbours are born, live cells
+/// with 2 or 3 survive, everything else dies.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Rule {
+    birth: u128,
+    survival: u128,
+}
+
+impl Rule {
+    /// Conway's Game of Life.
+    pub const LIFE: Rule = Rule {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+    };
+
+    /// Whether a cell is alive in the next generation. Neighbour counts past
+    /// what a rule can name (127) never match.
+    pub fn next(&self, alive: bool, neighbours: usize) -> bool {
+        let set = if alive { self.survival } else { self.birth };
+        neighbours < 128 && set & (1 << neighbours) != 0
+    }
+
+    /// Errors if the rule names a neighbour count above `neighbours`, which a
+    /// neighbourhood of that size never produces.
+    pub fn check(&self, neighbours: usize) -> Result<(), Box<dyn Error>> {
+        match Rule::counts(self.birth | self.survival).last() {
+            Some(n) if n as usize > neighbours => Err(format!(
+                "Rule {} counts up to {} neighbours, the neighbourhood has {}",
+                self, n, neighbours
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn counts(mask: u128) -> impl Iterator<Item = u32> {
+        (0..128).filter(move |n| mask & (1 << n) != 0)
+    }
+}
+
+fn parse_count(input: &str) -> Result<u32, Box<dyn Error>> {
+    match input.trim().parse::<u32>() {
+        Ok(n) if n < 128 => Ok(n),
+        _ => Err(format!("Invalid neighbour count: {}", input).into()),
+    }
+}
+
+/// Single digits (`23`), or for counts past 9 a comma separated list of
+/// counts and inclusive ranges (`2,3`, `10..12,20`).
+fn parse_counts(input: &str) -> Result<u128, Box<dyn Error>> {
+    if !input.contains(',') && !input.contains("..") {
+        return input.chars().try_fold(0, |mask, c| match c.to_digit(10) {
+            Some(n) => Ok(mask | 1 << n),
+            None => Err(format!("Invalid neighbour count: {}", c).into()),
+        });
+    }
+
+    input.split(',').try_fold(0, |mask, item| {
+        let (from, to) = match item.split_once("..") {
+            Some((from, to)) => (parse_count(from)?, parse_count(to)?),
+            None => (parse_count(item)?, parse_count(item)?),
+        };
+        if from > to {
+            return Err(format!("Invalid neighbour range: {}", item).into());
+        }
+
+        Ok((from..=to).fold(mask, |mask, n| mask | 1 << n))
+    })
+}
+
+fn write_counts(f: &mut fmt::Formatter, mask: u128) -> fmt::Result {
+    let counts: Vec<String> = Rule::counts(mask).map(|n| n.to_string()).collect();
+    if counts.iter().all(|n| n.len() == 1) {
+        write!(f, "{}", counts.concat())
+    } else {
+        write!(f, "{}", counts.join(","))
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Box<dyn Error>;
+
+    /// Takes `B3/S23` (in either order, any case), and the older `23/3`
+    /// survival/birth form. Counts past 9 are listed as `B3/S10,12..14`.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = input.trim().split('/').collect();
+        if parts.len() != 2 {
+            return Err(format!("Invalid rule: {}", input).into());
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        for part in parts.iter() {
+            match part.chars().next() {
+                Some('B') | Some('b') => birth = Some(parse_counts(&part[1..])?),
+                Some('S') | Some('s') => survival = Some(parse_counts(&part[1..])?),
+                _ => {}
+            }
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Rule { birth, survival }),
+            (None, None) => Ok(Rule {
+                survival: parse_counts(parts[0])?,
+                birth: parse_counts(parts[1])?,
+            }),
+            _ => Err(format!("Invalid rule: {}", input).into()),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        write_counts(f, self.birth)?;
+        write!(f, "/S")?;
+        write_counts(f, self.survival)
+    }
+}
+
+/// A grid cell the automaton can read and update. Cells that ignore `with`
+/// (like walls) take part as dead neighbours but never change.
+pub trait Cell: PartialEq {
+    fn alive(&self) -> bool;
+    fn with(&self, alive: bool) -> Self;
+}
+
+impl Cell for bool {
+    fn alive(&self) -> bool {
+        *self
+    }
+
+    fn with(&self, alive: bool) -> Self {
+        alive
+    }
+}
+
+/// The eight surrounding cells.
+pub const MOORE: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// The four orthogonally adjacent cells.
+pub const VON_NEUMANN: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+/// Live cells among the `neighbourhood` offsets around `x`, `y`; cells off
+/// the grid count as dead.
+pub fn neighbours<T: Cell>(
+    grid: &Grid<T>,
+    x: usize,
+    y: usize,
+    neighbourhood: &[(isize, isize)],
+) -> usize {
+    neighbourhood
+        .iter()
+        .filter_map(|(dx, dy)| {
+            let x = (x as isize).checked_add(*dx)?;
+            let y = (y as isize).checked_add(*dy)?;
+            if x < 0 || y < 0 {
+                return None;
+            }
+            grid.get(x as usize, y as usize)
+        })
+        .filter(|cell| cell.alive())
+        .count()
+}
+
+/// Advances the grid one generation, returning how many cells changed.
+pub fn step<T: Cell>(grid: &mut Grid<T>, rule: &Rule, neighbourhood: &[(isize, isize)]) -> usize {
+    grid.step(|grid, x, y| {
+        let cell = grid.get(x, y).unwrap();
+        let count = neighbours(grid, x, y, neighbourhood);
+        cell.with(rule.next(cell.alive(), count))
+    })
+}
+
+/// A pattern read from the run-length encoded format used by Life software.
+#[derive(Debug, PartialEq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub rule: Option<Rule>,
+    /// Live cells as `(x, y)`, top left being `(0, 0)`.
+    pub alive: Vec<(usize, usize)>,
+}
+
+impl Pattern {
+    pub fn parse_rle(input: &str) -> Result<Self, Box<dyn Error>> {
+        let mut lines = input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let header = lines.next().ok_or("Missing RLE header")?;
+        let mut width = None;
+        let mut height = None;
+        let mut rule = None;
+        for item in header.split(',') {
+            let (key, value) = item.split_once('=').ok_or("Invalid RLE header")?;
+            match key.trim() {
+                "x" => width = Some(value.trim().parse()?),
+                "y" => height = Some(value.trim().parse()?),
+                "rule" => rule = Some(value.trim().parse()?),
+                other => return Err(format!("Unknown RLE header item: {}", other).into()),
+            }
+        }
+
+        let mut alive = vec![];
+        let (mut x, mut y) = (0, 0);
+        let mut run = 0;
+        'body: for line in lines {
+            for c in line.chars() {
+                match c {
+                    '0'..='9' => {
+                        run = run * 10 + c.to_digit(10).unwrap() as usize;
+                        continue;
+                    }
+                    'b' | '.' => x += run.max(1),
+                    '$' => {
+                        y += run.max(1);
+                        x = 0;
+                    }
+                    '!' => break 'body,
+                    c if c.is_ascii_alphabetic() => {
+                        alive.extend((x..x + run.max(1)).map(|x| (x, y)));
+                        x += run.max(1);
+                    }
+                    c if c.is_whitespace() => {}
+                    _ => return Err(format!("Unexpected RLE character: {}", c).into()),
+                }
+                run = 0;
+            }
+        }
+
+        let width = width.ok_or("Missing pattern width")?;
+        let height = height.ok_or("Missing pattern height")?;
+        if alive.iter().any(|&(x, y)| x >= width || y >= height) {
+            return Err("Pattern larger than its header".into());
+        }
+
+        Ok(Self {
+            width,
+            height,
+            rule,
+            alive,
+        })
+    }
+
+    /// The pattern on a grid with `margin` dead cells around it.
+    pub fn to_grid<T: Cell>(&self, margin: usize, dead: T) -> Grid<T> {
+        let alive: HashSet<_> = self.alive.iter().collect();
+
+        Grid::from_fn(self.width + 2 * margin, self.height + 2 * margin, |x, y| {
+            let inside = x >= margin && y >= margin;
+            dead.with(inside && alive.contains(&(x - margin, y - margin)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn alive(grid: &Grid<bool>) -> Vec<(usize, usize)> {
+        (0..grid.height)
+            .flat_map(|y| (0..grid.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| *grid.get(x, y).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!("B3/S23".parse::<Rule>().unwrap(), Rule::LIFE);
+        assert_eq!("s23/b3".parse::<Rule>().unwrap(), Rule::LIFE);
+        assert_eq!("23/3".parse::<Rule>().unwrap(), Rule::LIFE);
+        assert_eq!("B0/S0123".parse::<Rule>().unwrap().to_string(), "B0/S0123");
+        assert_eq!("B36/S".parse::<Rule>().unwrap().to_string(), "B36/S");
+        assert!("B3".parse::<Rule>().is_err());
+        assert!("B3x/S23".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_parse_large_counts() {
+        assert_eq!("B3/S2,3".parse::<Rule>().unwrap(), Rule::LIFE);
+        assert_eq!("B3/S2..3".parse::<Rule>().unwrap(), Rule::LIFE);
+
+        let rule: Rule = "B10,12/S20..22".parse().unwrap();
+        assert!(rule.next(false, 10));
+        assert!(!rule.next(false, 11));
+        assert!(rule.next(true, 21));
+        assert_eq!(rule.to_string(), "B10,12/S20,21,22");
+        assert_eq!(rule.to_string().parse::<Rule>().unwrap(), rule);
+
+        assert!("B3,128/S".parse::<Rule>().is_err());
+        assert!("B3/S2,,3".parse::<Rule>().is_err());
+        assert!("B3/S3..2".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(Rule::LIFE.check(MOORE.len()).is_ok());
+        assert!(Rule::LIFE.check(VON_NEUMANN.len()).is_ok());
+        assert!("B5/S".parse::<Rule>().unwrap().check(4).is_err());
+
+        let rule: Rule = "B30/S26..80".parse().unwrap();
+        assert!(rule.check(26).is_err());
+        assert!(rule.check(80).is_ok());
+    }
+
+    #[test]
+    fn test_next() {
+        assert!(Rule::LIFE.next(false, 3));
+        assert!(!Rule::LIFE.next(false, 2));
+        assert!(Rule::LIFE.next(true, 2));
+        assert!(!Rule::LIFE.next(true, 4));
+        assert!(!Rule::LIFE.next(true, 500));
+    }
+
+    #[test]
+    fn test_parse_rle() {
+        let glider =
+            Pattern::parse_rle("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+
+        assert_eq!(glider.width, 3);
+        assert_eq!(glider.rule, Some(Rule::LIFE));
+        assert_eq!(glider.alive, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+
+        let spaced = Pattern::parse_rle("x = 5, y = 4\n2o3b$\n2$4o!").unwrap();
+        assert_eq!(
+            spaced.alive,
+            vec![(0, 0), (1, 0), (0, 3), (1, 3), (2, 3), (3, 3)]
+        );
+
+        assert!(Pattern::parse_rle("x = 1, y = 1\n3o!").is_err());
+        assert!(Pattern::parse_rle("bo!").is_err());
+    }
+
+    #[test]
+    fn test_blinker() {
+        let blinker = Pattern::parse_rle("x = 3, y = 1\n3o!").unwrap();
+        let mut grid = blinker.to_grid(1, false);
+
+        assert_eq!(step(&mut grid, &Rule::LIFE, &MOORE), 4);
+        assert_eq!(alive(&grid), vec![(2, 0), (2, 1), (2, 2)]);
+        step(&mut grid, &Rule::LIFE, &MOORE);
+        assert_eq!(alive(&grid), vec![(1, 1), (2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn test_glider() {
+        let glider = Pattern::parse_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+        let mut grid = glider.to_grid(2, false);
+        let start = alive(&grid);
+
+        for _ in 0..4 {
+            step(&mut grid, &Rule::LIFE, &MOORE);
+        }
+
+        let moved: Vec<_> = start.iter().map(|&(x, y)| (x + 1, y + 1)).collect();
+        assert_eq!(alive(&grid), moved);
+    }
+
+    #[test]
+    fn test_block_still() {
+        let block = Pattern::parse_rle("x = 2, y = 2\n2o$2o!").unwrap();
+        let mut grid = block.to_grid(1, false);
+
+        assert_eq!(step(&mut grid, &Rule::LIFE, &MOORE), 0);
+        assert_eq!(step(&mut grid, &"B1/S".parse().unwrap(), &VON_NEUMANN), 12);
+    }
+}